@@ -0,0 +1,6 @@
+pub mod pak;
+pub mod resolver;
+pub mod vfs;
+
+pub use resolver::AssetKind;
+pub use vfs::Vfs;
@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+// Archive layout (all integers little-endian):
+//   magic "LPAK", version: u32, entry count: u32
+//   index: for each entry { path length: u16, path: utf-8, offset: u64, size: u64 }
+//   file data, back to back
+const MAGIC: &[u8; 4] = b"LPAK";
+const VERSION: u32 = 1;
+
+#[derive(Debug, Clone)]
+struct PakEntry {
+    path: String,
+    offset: u64,
+    size: u64,
+}
+
+/// A packed asset archive opened for reading.
+/// Only the index is kept in memory; file data is read on demand.
+pub struct PakArchive {
+    path: PathBuf,
+    entries: HashMap<String, PakEntry>, // Keyed by lowercase path
+}
+
+impl PakArchive {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref().to_path_buf();
+        let file = File::open(&path)
+            .map_err(|e| format!("Failed to open archive {}: {}", path.display(), e))?;
+        let length = file
+            .metadata()
+            .map_err(|e| format!("Failed to stat archive {}: {}", path.display(), e))?
+            .len();
+        let mut reader = BufReader::new(file);
+
+        let mut magic = [0u8; 4];
+        reader
+            .read_exact(&mut magic)
+            .map_err(|e| format!("Failed to read archive header: {}", e))?;
+        if &magic != MAGIC {
+            return Err(format!("{} is not a pak archive", path.display()));
+        }
+
+        let version = read_u32(&mut reader)?;
+        if version != VERSION {
+            return Err(format!(
+                "Unsupported pak version {} in {}",
+                version,
+                path.display()
+            ));
+        }
+
+        // The count and sizes come from the file, so nothing is allocated up front on their word
+        let count = read_u32(&mut reader)?;
+        let mut entries = HashMap::new();
+        for _ in 0..count {
+            let len = read_u16(&mut reader)? as usize;
+            let mut name = vec![0u8; len];
+            reader
+                .read_exact(&mut name)
+                .map_err(|e| format!("Failed to read archive index: {}", e))?;
+            let name = String::from_utf8(name)
+                .map_err(|_| "Archive path is not valid UTF-8".to_string())?;
+            let offset = read_u64(&mut reader)?;
+            let size = read_u64(&mut reader)?;
+            match offset.checked_add(size) {
+                Some(end) if end <= length => {}
+                _ => return Err(format!("{} runs past the end of {}", name, path.display())),
+            }
+
+            entries.insert(
+                name.to_lowercase(),
+                PakEntry {
+                    path: name,
+                    offset,
+                    size,
+                },
+            );
+        }
+
+        Ok(Self { path, entries })
+    }
+
+    /// Look up an entry case-insensitively, returning the stored path
+    pub fn find(&self, path: &str) -> Option<&str> {
+        self.entries
+            .get(&path.to_lowercase())
+            .map(|entry| entry.path.as_str())
+    }
+
+    pub fn read(&self, path: &str) -> Result<Vec<u8>, String> {
+        let entry = self
+            .entries
+            .get(&path.to_lowercase())
+            .ok_or_else(|| format!("{} not found in {}", path, self.path.display()))?;
+
+        let mut file = File::open(&self.path)
+            .map_err(|e| format!("Failed to open archive {}: {}", self.path.display(), e))?;
+        file.seek(SeekFrom::Start(entry.offset))
+            .map_err(|e| format!("Failed to seek in archive: {}", e))?;
+
+        let mut data = vec![0u8; entry.size as usize];
+        file.read_exact(&mut data)
+            .map_err(|e| format!("Failed to read {} from archive: {}", path, e))?;
+        Ok(data)
+    }
+}
+
+/// Pack every file under `root` (or only under the `include` subdirectories)
+/// into an archive. Entries are stored with paths relative to `root`.
+pub fn build_archive(root: &Path, include: &[String], output: &Path) -> Result<usize, String> {
+    let mut files = Vec::new();
+    if include.is_empty() {
+        collect_files(root, root, &mut files)?;
+    } else {
+        for dir in include {
+            collect_files(root, &root.join(dir), &mut files)?;
+        }
+    }
+    files.sort();
+    if let Some((name, _)) = files
+        .iter()
+        .find(|(name, _)| name.len() > u16::MAX as usize)
+    {
+        return Err(format!("{} is too long a path for an archive", name));
+    }
+
+    // Data starts right after the index, so compute its size first
+    let index_size: u64 = files
+        .iter()
+        .map(|(name, _)| 2 + name.len() as u64 + 16)
+        .sum();
+    let mut offset = 12 + index_size;
+
+    let mut entries = Vec::with_capacity(files.len());
+    for (name, path) in &files {
+        let size = fs::metadata(path)
+            .map_err(|e| format!("Failed to stat {}: {}", path.display(), e))?
+            .len();
+        entries.push((name, offset, size));
+        offset += size;
+    }
+
+    let file = File::create(output)
+        .map_err(|e| format!("Failed to create {}: {}", output.display(), e))?;
+    let mut writer = BufWriter::new(file);
+    let write_err = |e: std::io::Error| format!("Failed to write archive: {}", e);
+
+    writer.write_all(MAGIC).map_err(write_err)?;
+    writer
+        .write_all(&VERSION.to_le_bytes())
+        .map_err(write_err)?;
+    writer
+        .write_all(&(entries.len() as u32).to_le_bytes())
+        .map_err(write_err)?;
+    for (name, offset, size) in &entries {
+        writer
+            .write_all(&(name.len() as u16).to_le_bytes())
+            .map_err(write_err)?;
+        writer.write_all(name.as_bytes()).map_err(write_err)?;
+        writer.write_all(&offset.to_le_bytes()).map_err(write_err)?;
+        writer.write_all(&size.to_le_bytes()).map_err(write_err)?;
+    }
+    for (_, path) in &files {
+        let data =
+            fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        writer.write_all(&data).map_err(write_err)?;
+    }
+    writer.flush().map_err(write_err)?;
+
+    Ok(entries.len())
+}
+
+/// Collect every file under `dir` as (path relative to `root` with `/` separators, full path)
+pub(crate) fn collect_files(
+    root: &Path,
+    dir: &Path,
+    files: &mut Vec<(String, PathBuf)>,
+) -> Result<(), String> {
+    let entries =
+        fs::read_dir(dir).map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_files(root, &path, files)?;
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            let name = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            files.push((name, path));
+        }
+    }
+    Ok(())
+}
+
+fn read_u16<R: Read>(reader: &mut R) -> Result<u16, String> {
+    let mut bytes = [0u8; 2];
+    reader
+        .read_exact(&mut bytes)
+        .map_err(|e| format!("Truncated archive: {}", e))?;
+    Ok(u16::from_le_bytes(bytes))
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32, String> {
+    let mut bytes = [0u8; 4];
+    reader
+        .read_exact(&mut bytes)
+        .map_err(|e| format!("Truncated archive: {}", e))?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64, String> {
+    let mut bytes = [0u8; 8];
+    reader
+        .read_exact(&mut bytes)
+        .map_err(|e| format!("Truncated archive: {}", e))?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archive_round_trip() {
+        let output = std::env::temp_dir().join("lucid_pak_round_trip.pak");
+        let count = build_archive(Path::new("."), &["assets/maps".to_string()], &output).unwrap();
+        assert!(count >= 1);
+
+        let archive = PakArchive::open(&output).unwrap();
+        assert_eq!(
+            archive.find("ASSETS/MAPS/TEST.TOML"),
+            Some("assets/maps/test.toml")
+        );
+
+        let packed = archive.read("assets/maps/test.toml").unwrap();
+        let original = fs::read("assets/maps/test.toml").unwrap();
+        assert_eq!(packed, original);
+
+        // An index pointing past the end of a cut-off archive is refused
+        let file = fs::OpenOptions::new().write(true).open(&output).unwrap();
+        file.set_len(fs::metadata(&output).unwrap().len() - 1)
+            .unwrap();
+        assert!(PakArchive::open(&output).is_err());
+
+        let _ = fs::remove_file(output);
+    }
+}
@@ -0,0 +1,143 @@
+use log::{error, info, warn};
+use std::fmt;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssetKind {
+    Map,
+    Texture,
+    Sound,
+    Music,
+}
+
+impl fmt::Display for AssetKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            AssetKind::Map => "map",
+            AssetKind::Texture => "texture",
+            AssetKind::Sound => "sound",
+            AssetKind::Music => "music",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Result of validating every asset the game references at startup
+#[derive(Debug, Default)]
+pub struct AssetReport {
+    pub checked: usize,
+    pub case_mismatches: Vec<(String, String)>, // (requested, found)
+    pub missing: Vec<(AssetKind, String)>,
+}
+
+impl AssetReport {
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty()
+    }
+
+    /// Log the whole report at once instead of one error per failed load
+    pub fn log(&self) {
+        for (requested, found) in &self.case_mismatches {
+            warn!(
+                "Asset '{}' only matched case-insensitively as '{}'",
+                requested, found
+            );
+        }
+
+        if self.is_ok() {
+            info!("All {} referenced assets found", self.checked);
+            return;
+        }
+
+        let list: Vec<String> = self
+            .missing
+            .iter()
+            .map(|(kind, path)| format!("  {} {}", kind, path))
+            .collect();
+        error!(
+            "{} of {} referenced assets are missing:\n{}",
+            self.missing.len(),
+            self.checked,
+            list.join("\n")
+        );
+    }
+}
+
+/// Resolves asset paths relative to a root directory.
+/// Paths whose case doesn't match the files on disk (e.g. `step.wav` vs `Step.wav`)
+/// fall back to a case-insensitive match so they still load on case-sensitive filesystems.
+pub struct AssetResolver {
+    root: PathBuf,
+}
+
+impl AssetResolver {
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Find the file on disk for `path`, or None if it doesn't exist in any casing.
+    /// Absolute paths and `..` are refused, so nothing outside the root resolves.
+    pub fn resolve(&self, path: &str) -> Option<PathBuf> {
+        let inside_root = Path::new(path)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+        if !inside_root {
+            return None;
+        }
+
+        let exact = self.root.join(path);
+        if exact.exists() {
+            return Some(exact);
+        }
+
+        // Walk the path one component at a time, matching names case-insensitively
+        let mut current = self.root.clone();
+        for component in Path::new(path).components() {
+            let Component::Normal(name) = component else {
+                continue;
+            };
+            let name = name.to_string_lossy();
+
+            let direct = current.join(name.as_ref());
+            if direct.exists() {
+                current = direct;
+                continue;
+            }
+
+            let entry = fs::read_dir(&current).ok()?.flatten().find(|entry| {
+                entry
+                    .file_name()
+                    .to_string_lossy()
+                    .eq_ignore_ascii_case(&name)
+            })?;
+            current = entry.path();
+        }
+
+        Some(current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_case_insensitive_fallback() {
+        let resolver = AssetResolver::new(".");
+        let step = resolver.resolve("assets/audio/effects/step.wav").unwrap();
+        assert!(step.ends_with("assets/audio/effects/Step.wav"));
+        assert!(resolver
+            .resolve("assets/textures/walls/brick.png")
+            .is_some());
+        assert!(resolver.resolve("assets/audio/music/missing.wav").is_none());
+        assert!(resolver.resolve("../crate/Cargo.toml").is_none());
+        assert!(resolver.resolve("/etc/hostname").is_none());
+    }
+}
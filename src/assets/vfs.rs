@@ -0,0 +1,229 @@
+use crate::assets::pak::PakArchive;
+use crate::assets::resolver::{AssetKind, AssetReport, AssetResolver};
+use log::{info, warn};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const DATA_DIR_ENV: &str = "LUCID_DATA_DIR";
+const PACKS_DIR: &str = "packs";
+
+/// A source of files mounted into the virtual filesystem
+pub enum Mount {
+    Directory(AssetResolver),
+    Archive(PakArchive),
+}
+
+impl Mount {
+    /// Find `path` in this mount, returning the name it was actually stored under
+    fn find(&self, path: &str) -> Option<String> {
+        match self {
+            Mount::Directory(resolver) => {
+                let found = resolver.resolve(path)?;
+                let relative = found.strip_prefix(resolver.root()).unwrap_or(&found);
+                Some(
+                    relative
+                        .components()
+                        .map(|c| c.as_os_str().to_string_lossy())
+                        .collect::<Vec<_>>()
+                        .join("/"),
+                )
+            }
+            Mount::Archive(archive) => archive.find(path).map(str::to_string),
+        }
+    }
+
+    fn read(&self, path: &str) -> Result<Vec<u8>, String> {
+        match self {
+            Mount::Directory(resolver) => {
+                let found = resolver
+                    .resolve(path)
+                    .ok_or_else(|| format!("{} not found", path))?;
+                fs::read(&found).map_err(|e| format!("Failed to read {}: {}", found.display(), e))
+            }
+            Mount::Archive(archive) => archive.read(path),
+        }
+    }
+}
+
+struct MountPoint {
+    name: String,
+    priority: i32,
+    mount: Mount,
+}
+
+/// Virtual filesystem over directories and pak archives.
+/// Lookups go through mounts from the highest priority down, so later
+/// mounts (packs, mods) override files from the base data directory.
+pub struct Vfs {
+    mounts: Vec<MountPoint>,
+    base: Option<PathBuf>,
+}
+
+impl Vfs {
+    pub fn new() -> Self {
+        Self {
+            mounts: Vec::new(),
+            base: None,
+        }
+    }
+
+    /// Mount the base data directory and every archive in its `packs` folder
+    pub fn with_base_data() -> Self {
+        let mut vfs = Self::new();
+        let base = find_data_dir();
+        info!("Using data directory {}", base.display());
+        vfs.mount_dir(&base, 0);
+        vfs.base = Some(base.clone());
+
+        let packs_dir = base.join(PACKS_DIR);
+        if let Ok(entries) = fs::read_dir(&packs_dir) {
+            let mut packs: Vec<PathBuf> = entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == "pak"))
+                .collect();
+            packs.sort();
+            for (i, pack) in packs.iter().enumerate() {
+                if let Err(e) = vfs.mount_pak(pack, 1 + i as i32) {
+                    warn!("Skipping pack: {}", e);
+                }
+            }
+        }
+
+        vfs
+    }
+
+    /// The data directory mounted by `with_base_data`, where `packs/` and `mods/` live
+    pub fn base_dir(&self) -> Option<&Path> {
+        self.base.as_deref()
+    }
+
+    pub fn mount_dir<P: AsRef<Path>>(&mut self, dir: P, priority: i32) {
+        let dir = dir.as_ref();
+        self.insert(MountPoint {
+            name: dir.display().to_string(),
+            priority,
+            mount: Mount::Directory(AssetResolver::new(dir)),
+        });
+    }
+
+    pub fn mount_pak<P: AsRef<Path>>(&mut self, path: P, priority: i32) -> Result<(), String> {
+        let archive = PakArchive::open(&path)?;
+        info!("Mounted pack {}", path.as_ref().display());
+        self.insert(MountPoint {
+            name: path.as_ref().display().to_string(),
+            priority,
+            mount: Mount::Archive(archive),
+        });
+        Ok(())
+    }
+
+    pub fn exists(&self, path: &str) -> bool {
+        self.mounts.iter().any(|mp| mp.mount.find(path).is_some())
+    }
+
+    /// Read a whole file from the highest-priority mount that has it
+    pub fn read(&self, path: &str) -> Result<Vec<u8>, String> {
+        for mp in &self.mounts {
+            if mp.mount.find(path).is_some() {
+                return mp
+                    .mount
+                    .read(path)
+                    .map_err(|e| format!("{} (in {})", e, mp.name));
+            }
+        }
+        Err(format!("Asset {} not found in any mount", path))
+    }
+
+    pub fn read_to_string(&self, path: &str) -> Result<String, String> {
+        let data = self.read(path)?;
+        String::from_utf8(data).map_err(|_| format!("Asset {} is not valid UTF-8", path))
+    }
+
+    /// Check every referenced asset and collect missing files and case fallbacks
+    pub fn validate(&self, assets: &[(AssetKind, &str)]) -> AssetReport {
+        let mut report = AssetReport::default();
+
+        for &(kind, path) in assets {
+            report.checked += 1;
+            let found = self.mounts.iter().find_map(|mp| mp.mount.find(path));
+            match found {
+                Some(found) if found != path.trim_start_matches("./") => {
+                    report.case_mismatches.push((path.to_string(), found));
+                }
+                Some(_) => {}
+                None => report.missing.push((kind, path.to_string())),
+            }
+        }
+
+        report
+    }
+
+    fn insert(&mut self, mount: MountPoint) {
+        // Keep mounts sorted by descending priority; the latest mount wins ties
+        let index = self
+            .mounts
+            .iter()
+            .position(|mp| mp.priority <= mount.priority)
+            .unwrap_or(self.mounts.len());
+        self.mounts.insert(index, mount);
+    }
+}
+
+impl Default for Vfs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Locate the directory holding `assets/`: an explicit override, the working
+/// directory, or the executable's directory and its parents (for `target/debug`).
+fn find_data_dir() -> PathBuf {
+    if let Ok(dir) = env::var(DATA_DIR_ENV) {
+        return PathBuf::from(dir);
+    }
+
+    let cwd = env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    if cwd.join("assets").is_dir() {
+        return cwd;
+    }
+
+    if let Ok(exe) = env::current_exe() {
+        for dir in exe.ancestors().skip(1) {
+            if dir.join("assets").is_dir() {
+                return dir.to_path_buf();
+            }
+        }
+    }
+
+    cwd
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assets::pak::build_archive;
+
+    #[test]
+    fn test_higher_priority_mount_overrides() {
+        let pack = env::temp_dir().join("lucid_vfs_override.pak");
+        build_archive(Path::new("."), &["assets/maps".to_string()], &pack).unwrap();
+
+        let mut vfs = Vfs::new();
+        vfs.mount_dir(".", 0);
+        vfs.mount_pak(&pack, 10).unwrap();
+        assert_eq!(vfs.mounts[0].name, pack.display().to_string());
+        assert!(vfs.exists("assets/maps/test.toml"));
+        assert!(vfs.exists("assets/textures/walls/brick.png"));
+
+        let report = vfs.validate(&[
+            (AssetKind::Sound, "assets/audio/effects/step.wav"),
+            (AssetKind::Music, "assets/audio/music/missing.wav"),
+        ]);
+        assert_eq!(report.case_mismatches.len(), 1);
+        assert_eq!(report.missing.len(), 1);
+
+        let _ = fs::remove_file(pack);
+    }
+}
@@ -0,0 +1,129 @@
+use crate::engine::grid;
+use glam::Vec2;
+use std::time::Duration;
+
+const OCCLUSION_ATTENUATION: f32 = 0.5; // Volume multiplier per wall between source and listener
+const OCCLUDED_CUTOFF: u32 = 1500; // Low-pass cutoff (Hz) behind a single wall
+const MIN_CUTOFF: u32 = 300;
+const DISTANCE_ROLLOFF: f32 = 0.15;
+
+const REVERB_PROBES: usize = 16; // Rays cast around the listener to estimate room size
+const REVERB_MAX_DISTANCE: f32 = 12.0;
+const REVERB_MAX_WET: f32 = 0.45;
+
+/// How a sound is affected by the geometry between its source and the listener
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Occlusion {
+    pub walls: usize,
+    pub attenuation: f32,
+    pub cutoff: Option<u32>,
+}
+
+impl Occlusion {
+    pub fn none() -> Self {
+        Self {
+            walls: 0,
+            attenuation: 1.0,
+            cutoff: None,
+        }
+    }
+}
+
+/// Reverb parameters estimated from the open area around the listener
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RoomReverb {
+    pub delay: Duration,
+    pub wet: f32,
+}
+
+impl RoomReverb {
+    pub fn dry() -> Self {
+        Self {
+            delay: Duration::ZERO,
+            wet: 0.0,
+        }
+    }
+}
+
+/// Compute occlusion for a sound travelling from `source` to `listener`.
+/// Each wall in the way halves the volume and lowers the low-pass cutoff.
+pub fn occlusion(source: Vec2, listener: Vec2, map: &[Vec<i32>]) -> Occlusion {
+    let walls = grid::walls_between(source, listener, map);
+    let distance = (listener - source).length();
+    let rolloff = 1.0 / (1.0 + distance * DISTANCE_ROLLOFF);
+
+    if walls == 0 {
+        return Occlusion {
+            walls,
+            attenuation: rolloff,
+            cutoff: None,
+        };
+    }
+
+    Occlusion {
+        walls,
+        attenuation: rolloff * OCCLUSION_ATTENUATION.powi(walls as i32),
+        cutoff: Some((OCCLUDED_CUTOFF / walls as u32).max(MIN_CUTOFF)),
+    }
+}
+
+/// Estimate reverb from how far the listener can see in every direction.
+/// Tight corridors stay mostly dry, large open rooms get a longer, wetter tail.
+pub fn room_reverb(listener: Vec2, map: &[Vec<i32>]) -> RoomReverb {
+    if grid::is_blocked(listener, map) {
+        return RoomReverb::dry();
+    }
+
+    let total: f32 = (0..REVERB_PROBES)
+        .map(|i| {
+            let angle = i as f32 / REVERB_PROBES as f32 * std::f32::consts::TAU;
+            let direction = Vec2::new(angle.cos(), angle.sin());
+            grid::free_distance(listener, direction, REVERB_MAX_DISTANCE, map)
+        })
+        .sum();
+    let average = total / REVERB_PROBES as f32;
+    let openness = (average / REVERB_MAX_DISTANCE).clamp(0.0, 1.0);
+
+    RoomReverb {
+        delay: Duration::from_millis(30 + (average * 15.0) as u64),
+        wet: openness * REVERB_MAX_WET,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn room() -> Vec<Vec<i32>> {
+        vec![
+            vec![1, 1, 1, 1, 1, 1, 1],
+            vec![1, 0, 0, 1, 0, 0, 1],
+            vec![1, 0, 0, 1, 0, 0, 1],
+            vec![1, 0, 0, 0, 0, 0, 1],
+            vec![1, 1, 1, 1, 1, 1, 1],
+        ]
+    }
+
+    #[test]
+    fn test_wall_occludes_sound() {
+        let map = room();
+        let blocked = occlusion(Vec2::new(1.5, 1.5), Vec2::new(5.5, 1.5), &map);
+        assert_eq!(blocked.walls, 1);
+        assert!(blocked.cutoff.is_some());
+
+        let clear = occlusion(Vec2::new(1.5, 3.5), Vec2::new(5.5, 3.5), &map);
+        assert_eq!(clear.walls, 0);
+        assert!(clear.cutoff.is_none());
+        assert!(clear.attenuation > blocked.attenuation);
+    }
+
+    #[test]
+    fn test_open_rooms_are_wetter() {
+        let small = room();
+        let large = vec![vec![0; 20]; 20];
+        let small_reverb = room_reverb(Vec2::new(1.5, 1.5), &small);
+        let large_reverb = room_reverb(Vec2::new(10.5, 10.5), &large);
+        assert!(large_reverb.wet > small_reverb.wet);
+        assert!(large_reverb.delay > small_reverb.delay);
+    }
+}
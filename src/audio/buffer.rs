@@ -0,0 +1,108 @@
+use crate::assets::Vfs;
+use rodio::{Decoder, Source};
+use std::io::{Cursor, Read, Seek};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A fully decoded sound kept in memory and shared between every playback
+#[derive(Clone)]
+pub struct SoundBuffer {
+    samples: Arc<[f32]>,
+    channels: u16,
+    sample_rate: u32,
+}
+
+impl SoundBuffer {
+    /// Load and decode a sound through the virtual filesystem
+    pub fn load(vfs: &Vfs, path: &str) -> Result<Self, String> {
+        Self::decode(Cursor::new(vfs.read(path)?))
+    }
+
+    pub fn decode<R>(reader: R) -> Result<Self, String>
+    where
+        R: Read + Seek + Send + Sync + 'static,
+    {
+        let decoder =
+            Decoder::new(reader).map_err(|e| format!("Failed to decode audio file: {}", e))?;
+        let channels = decoder.channels();
+        let sample_rate = decoder.sample_rate();
+        let samples: Vec<f32> = decoder.convert_samples().collect();
+
+        Ok(Self {
+            samples: samples.into(),
+            channels,
+            sample_rate,
+        })
+    }
+
+    pub fn duration(&self) -> Duration {
+        let frames = self.samples.len() as f64 / self.channels.max(1) as f64;
+        Duration::from_secs_f64(frames / self.sample_rate.max(1) as f64)
+    }
+
+    /// Create a new playback cursor over the shared samples
+    pub fn source(&self) -> SoundBufferSource {
+        SoundBufferSource {
+            samples: Arc::clone(&self.samples),
+            channels: self.channels,
+            sample_rate: self.sample_rate,
+            position: 0,
+            looping: false,
+        }
+    }
+
+    /// Create a cursor that wraps around at the end without a gap.
+    /// Layers started together stay sample-aligned.
+    pub fn looped_source(&self) -> SoundBufferSource {
+        SoundBufferSource {
+            looping: true,
+            ..self.source()
+        }
+    }
+}
+
+/// A rodio source reading from a shared `SoundBuffer`
+pub struct SoundBufferSource {
+    samples: Arc<[f32]>,
+    channels: u16,
+    sample_rate: u32,
+    position: usize,
+    looping: bool,
+}
+
+impl Iterator for SoundBufferSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.looping && self.position >= self.samples.len() && !self.samples.is_empty() {
+            self.position = 0;
+        }
+        let sample = self.samples.get(self.position).copied();
+        self.position += 1;
+        sample
+    }
+}
+
+impl Source for SoundBufferSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        if self.looping {
+            return None;
+        }
+        let frames = self.samples.len() as f64 / self.channels.max(1) as f64;
+        Some(Duration::from_secs_f64(
+            frames / self.sample_rate.max(1) as f64,
+        ))
+    }
+}
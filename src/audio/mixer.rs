@@ -0,0 +1,217 @@
+use rodio::Source;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Named mixer buses. Every sound is routed through one bus and the master bus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Bus {
+    Master,
+    Music,
+    Effects,
+    Weapons,
+}
+
+impl Bus {
+    pub const ALL: [Bus; 4] = [Bus::Master, Bus::Music, Bus::Effects, Bus::Weapons];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct BusState {
+    volume: f32,
+    muted: bool,
+    solo: bool,
+}
+
+impl Default for BusState {
+    fn default() -> Self {
+        Self {
+            volume: 1.0,
+            muted: false,
+            solo: false,
+        }
+    }
+}
+
+struct MixerInner {
+    buses: Mutex<HashMap<Bus, BusState>>,
+    gains: [AtomicU32; 4], // Effective bus gain (bus x master) stored as f32 bits
+}
+
+/// Shared mixer. Cloning it is cheap and every clone controls the same buses,
+/// so volume, mute and solo changes apply live to sounds that are already playing.
+#[derive(Clone)]
+pub struct Mixer {
+    inner: Arc<MixerInner>,
+}
+
+impl Mixer {
+    pub fn new() -> Self {
+        let buses = Bus::ALL
+            .iter()
+            .map(|&bus| (bus, BusState::default()))
+            .collect();
+        Self {
+            inner: Arc::new(MixerInner {
+                buses: Mutex::new(buses),
+                gains: std::array::from_fn(|_| AtomicU32::new(1.0f32.to_bits())),
+            }),
+        }
+    }
+
+    pub fn set_volume(&self, bus: Bus, volume: f32) {
+        self.modify(bus, |state| state.volume = volume.clamp(0.0, 1.0));
+    }
+
+    pub fn toggle_mute(&self, bus: Bus) -> bool {
+        let mut muted = false;
+        self.modify(bus, |state| {
+            state.muted = !state.muted;
+            muted = state.muted;
+        });
+        muted
+    }
+
+    /// Solo a bus. While any bus is soloed, every other non-master bus is silent.
+    pub fn toggle_solo(&self, bus: Bus) -> bool {
+        let mut solo = false;
+        self.modify(bus, |state| {
+            state.solo = !state.solo;
+            solo = state.solo;
+        });
+        solo
+    }
+
+    /// Current effective gain of a bus, including the master bus
+    pub fn gain(&self, bus: Bus) -> f32 {
+        f32::from_bits(self.inner.gains[bus.index()].load(Ordering::Relaxed))
+    }
+
+    /// Wrap a source so its samples follow the live gain of `bus`
+    pub fn route<S>(&self, source: S, bus: Bus) -> BusSource<S>
+    where
+        S: Source<Item = f32>,
+    {
+        BusSource {
+            inner: source,
+            mixer: self.clone(),
+            bus,
+        }
+    }
+
+    fn modify<F: FnOnce(&mut BusState)>(&self, bus: Bus, change: F) {
+        let mut buses = self.inner.buses.lock().unwrap();
+        if let Some(state) = buses.get_mut(&bus) {
+            change(state);
+        }
+
+        let gains = compute_gains(&buses);
+        for (bus, gain) in gains {
+            self.inner.gains[bus.index()].store(gain.to_bits(), Ordering::Relaxed);
+        }
+    }
+}
+
+impl Default for Mixer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn compute_gains(buses: &HashMap<Bus, BusState>) -> Vec<(Bus, f32)> {
+    let master = buses.get(&Bus::Master).copied().unwrap_or_default();
+    let master_gain = if master.muted { 0.0 } else { master.volume };
+    let any_solo = buses
+        .iter()
+        .any(|(&bus, state)| bus != Bus::Master && state.solo);
+
+    Bus::ALL
+        .iter()
+        .map(|&bus| {
+            if bus == Bus::Master {
+                return (bus, master_gain);
+            }
+
+            let state = buses.get(&bus).copied().unwrap_or_default();
+            let audible = !state.muted && (!any_solo || state.solo);
+            let gain = if audible {
+                state.volume * master_gain
+            } else {
+                0.0
+            };
+            (bus, gain)
+        })
+        .collect()
+}
+
+/// A source whose output is scaled by the current gain of a mixer bus
+pub struct BusSource<S> {
+    inner: S,
+    mixer: Mixer,
+    bus: Bus,
+}
+
+impl<S> Iterator for BusSource<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        self.inner
+            .next()
+            .map(|sample| sample * self.mixer.gain(self.bus))
+    }
+}
+
+impl<S> Source for BusSource<S>
+where
+    S: Source<Item = f32>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gains_compose_with_master() {
+        let mixer = Mixer::new();
+        mixer.set_volume(Bus::Master, 0.5);
+        mixer.set_volume(Bus::Weapons, 0.8);
+        assert!((mixer.gain(Bus::Weapons) - 0.4).abs() < 1e-6);
+        assert!((mixer.gain(Bus::Music) - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_mute_and_solo() {
+        let mixer = Mixer::new();
+        mixer.toggle_solo(Bus::Music);
+        assert_eq!(mixer.gain(Bus::Effects), 0.0);
+        assert_eq!(mixer.gain(Bus::Music), 1.0);
+
+        mixer.toggle_solo(Bus::Music);
+        mixer.toggle_mute(Bus::Master);
+        assert_eq!(mixer.gain(Bus::Effects), 0.0);
+    }
+}
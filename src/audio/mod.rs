@@ -1,52 +1,45 @@
-use rodio::{OutputStream, OutputStreamHandle, Sink};
-use std::collections::HashMap;
+use crate::config::AudioConfig;
+use rodio::OutputStream;
 
+pub mod acoustics;
+pub mod buffer;
+pub mod mixer;
+pub mod music;
 pub mod sound;
-pub use sound::{MusicPlayer, SoundEffect, SoundManager};
+pub mod voices;
+pub use mixer::{Bus, Mixer};
+pub use music::MusicPlayer;
+pub use sound::{SoundEffect, SoundManager};
+pub use voices::SoundPriority;
 
+/// Owns the output stream and the single mixer shared by sound effects and music
 pub struct AudioManager {
     _stream: OutputStream,
-    stream_handle: OutputStreamHandle,
-    sinks: HashMap<String, Sink>,
-    volume: f32,
+    pub sounds: SoundManager,
+    pub music: MusicPlayer,
+    mixer: Mixer,
 }
 
 impl AudioManager {
-    pub fn new() -> Result<Self, String> {
+    pub fn new(config: &AudioConfig) -> Result<Self, String> {
         let (stream, stream_handle) = OutputStream::try_default()
             .map_err(|e| format!("Failed to open audio output stream: {}", e))?;
 
+        let mixer = Mixer::new();
+        mixer.set_volume(Bus::Master, config.master_volume);
+        mixer.set_volume(Bus::Music, config.music_volume);
+        mixer.set_volume(Bus::Effects, config.effects_volume);
+        mixer.set_volume(Bus::Weapons, config.weapon_volume);
+
         Ok(Self {
             _stream: stream,
-            stream_handle,
-            sinks: HashMap::new(),
-            volume: 1.0,
+            sounds: SoundManager::new(stream_handle.clone(), mixer.clone()),
+            music: MusicPlayer::new(stream_handle, mixer.clone()),
+            mixer,
         })
     }
 
-    pub fn play_sound(&mut self, path: &str) -> Result<(), String> {
-        let sink = Sink::try_new(&self.stream_handle)
-            .map_err(|e| format!("Failed to create audio sink: {}", e))?;
-
-        // Load and decode the audio file
-        let file = std::fs::File::open(path)
-            .map_err(|e| format!("Failed to open audio file {}: {}", path, e))?;
-        let source = rodio::Decoder::new(std::io::BufReader::new(file))
-            .map_err(|e| format!("Failed to decode audio file {}: {}", path, e))?;
-
-        // Play the sound
-        sink.append(source);
-        sink.set_volume(self.volume);
-        self.sinks.insert(path.to_string(), sink);
-
-        Ok(())
-    }
-
-    /// Set the global volume for all sounds
-    pub fn set_volume(&mut self, volume: f32) {
-        self.volume = volume.clamp(0.0, 1.0);
-        for sink in self.sinks.values() {
-            sink.set_volume(self.volume);
-        }
+    pub fn mixer(&self) -> &Mixer {
+        &self.mixer
     }
 }
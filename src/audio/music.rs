@@ -0,0 +1,226 @@
+use crate::audio::buffer::SoundBuffer;
+use crate::audio::mixer::{Bus, Mixer};
+use log::error;
+use rodio::{OutputStreamHandle, Sink};
+
+const DEFAULT_CROSSFADE: f32 = 2.0; // Seconds
+const COMBAT_FADE_IN: f32 = 1.0;
+const COMBAT_FADE_OUT: f32 = 3.0;
+const COMBAT_HOLD: f32 = 4.0; // Keep the combat layer up this long after combat ends
+
+/// A playing music sink with its own fade envelope
+struct MusicTrack {
+    sink: Sink,
+    fade: f32,
+    fade_target: f32,
+    fade_speed: f32, // Fade units per second
+    remaining: f32,  // Seconds until the track ends, infinite for looping layers
+    crossfade: f32,  // Seconds it fades in over, and that the next track overlaps it
+}
+
+impl MusicTrack {
+    fn update(&mut self, dt: f32) {
+        if self.fade < self.fade_target {
+            self.fade = (self.fade + self.fade_speed * dt).min(self.fade_target);
+        } else if self.fade > self.fade_target {
+            self.fade = (self.fade - self.fade_speed * dt).max(self.fade_target);
+        }
+        self.remaining -= dt;
+        // The Music bus sets the level; the sink only carries the fade
+        self.sink.set_volume(self.fade);
+    }
+
+    fn fade_to(&mut self, target: f32, seconds: f32) {
+        self.fade_target = target;
+        self.fade_speed = if seconds > 0.0 {
+            1.0 / seconds
+        } else {
+            f32::MAX
+        };
+    }
+
+    fn is_silent(&self) -> bool {
+        self.fade <= 0.0 && self.fade_target <= 0.0
+    }
+}
+
+/// Combat-reactive layer played in sync with the base stem
+struct CombatLayer {
+    track: MusicTrack,
+    hold: f32,
+}
+
+/// Plays map music: crossfading playlists or layered stems that react to combat
+pub struct MusicPlayer {
+    stream_handle: OutputStreamHandle,
+    current_track: Option<MusicTrack>,
+    fading_tracks: Vec<MusicTrack>,
+    playlist: Vec<SoundBuffer>,
+    playlist_index: usize,
+    crossfade: f32,
+    combat_layer: Option<CombatLayer>,
+    mixer: Mixer,
+}
+
+impl MusicPlayer {
+    pub fn new(stream_handle: OutputStreamHandle, mixer: Mixer) -> Self {
+        Self {
+            stream_handle,
+            current_track: None,
+            fading_tracks: Vec::new(),
+            playlist: Vec::new(),
+            playlist_index: 0,
+            crossfade: DEFAULT_CROSSFADE,
+            combat_layer: None,
+            mixer,
+        }
+    }
+
+    /// Play tracks in order, crossfading from one to the next and wrapping around.
+    /// Empty tracks are skipped, since they would end the moment they started.
+    pub fn play_playlist(
+        &mut self,
+        mut tracks: Vec<SoundBuffer>,
+        crossfade: f32,
+    ) -> Result<(), String> {
+        tracks.retain(|track| !track.duration().is_zero());
+        if tracks.is_empty() {
+            return Err("Playlist has no tracks to play".to_string());
+        }
+
+        self.stop_combat_layer();
+        self.playlist = tracks;
+        self.playlist_index = 0;
+        self.crossfade = crossfade.max(0.0);
+        self.start_playlist_track()
+    }
+
+    /// Play a base stem with a combat stem layered on top.
+    /// The combat layer stays silent until `set_combat(true)`.
+    pub fn play_stems(
+        &mut self,
+        base: SoundBuffer,
+        combat: SoundBuffer,
+        crossfade: f32,
+    ) -> Result<(), String> {
+        self.stop_combat_layer();
+        self.playlist.clear();
+        self.crossfade = crossfade.max(0.0);
+
+        // Start both layers back to back so they stay aligned
+        let base_sink = self.create_sink()?;
+        let combat_sink = self.create_sink()?;
+        base_sink.append(self.mixer.route(base.looped_source(), Bus::Music));
+        combat_sink.append(self.mixer.route(combat.looped_source(), Bus::Music));
+
+        let mut base_track = self.new_track(base_sink, f32::INFINITY, self.crossfade);
+        base_track.fade_to(1.0, self.crossfade);
+        self.replace_current(base_track);
+
+        let mut combat_track = self.new_track(combat_sink, f32::INFINITY, self.crossfade);
+        combat_track.fade_to(0.0, 0.0);
+        self.combat_layer = Some(CombatLayer {
+            track: combat_track,
+            hold: 0.0,
+        });
+
+        Ok(())
+    }
+
+    /// Tell the stem mixer whether any enemy is currently fighting the player
+    pub fn set_combat(&mut self, in_combat: bool) {
+        if let Some(layer) = &mut self.combat_layer {
+            if in_combat {
+                layer.hold = COMBAT_HOLD;
+                layer.track.fade_to(1.0, COMBAT_FADE_IN);
+            } else if layer.hold <= 0.0 {
+                layer.track.fade_to(0.0, COMBAT_FADE_OUT);
+            }
+        }
+    }
+
+    /// Advance fades and the playlist. Call once per frame.
+    pub fn update(&mut self, dt: f32) {
+        if let Some(track) = &mut self.current_track {
+            track.update(dt);
+        }
+        for track in &mut self.fading_tracks {
+            track.update(dt);
+        }
+        self.fading_tracks.retain(|track| {
+            if track.is_silent() || track.sink.empty() {
+                track.sink.stop();
+                false
+            } else {
+                true
+            }
+        });
+
+        if let Some(layer) = &mut self.combat_layer {
+            layer.hold -= dt;
+            layer.track.update(dt);
+        }
+
+        // Start the next playlist track early enough to crossfade into it
+        let track_ending = self
+            .current_track
+            .as_ref()
+            .is_some_and(|track| track.remaining <= track.crossfade);
+        if track_ending && !self.playlist.is_empty() {
+            self.playlist_index = (self.playlist_index + 1) % self.playlist.len();
+            if let Err(e) = self.start_playlist_track() {
+                error!("Failed to play next music track: {}", e);
+                self.playlist.clear();
+            }
+        }
+    }
+
+    fn start_playlist_track(&mut self) -> Result<(), String> {
+        let buffer = self.playlist[self.playlist_index].clone();
+
+        let sink = self.create_sink()?;
+        sink.append(self.mixer.route(buffer.source(), Bus::Music));
+
+        // Tracks shorter than two crossfades fade over half their length instead,
+        // so the next one starts before this one runs out
+        let duration = buffer.duration().as_secs_f32();
+        let crossfade = self.crossfade.min(duration / 2.0);
+        let mut track = self.new_track(sink, duration, crossfade);
+        track.fade_to(1.0, crossfade);
+        self.replace_current(track);
+        Ok(())
+    }
+
+    /// Make `track` the current one, fading out whatever was playing before
+    fn replace_current(&mut self, track: MusicTrack) {
+        let crossfade = track.crossfade;
+        if let Some(mut previous) = self.current_track.replace(track) {
+            previous.fade_to(0.0, crossfade);
+            self.fading_tracks.push(previous);
+        }
+    }
+
+    fn stop_combat_layer(&mut self) {
+        if let Some(mut layer) = self.combat_layer.take() {
+            layer.track.fade_to(0.0, self.crossfade);
+            self.fading_tracks.push(layer.track);
+        }
+    }
+
+    fn create_sink(&self) -> Result<Sink, String> {
+        Sink::try_new(&self.stream_handle)
+            .map_err(|e| format!("Failed to create audio sink: {}", e))
+    }
+
+    fn new_track(&self, sink: Sink, remaining: f32, crossfade: f32) -> MusicTrack {
+        sink.set_volume(0.0);
+        MusicTrack {
+            sink,
+            fade: 0.0,
+            fade_target: 0.0,
+            fade_speed: 0.0,
+            remaining,
+            crossfade,
+        }
+    }
+}
@@ -1,21 +1,32 @@
-use rodio::{Decoder, OutputStreamHandle, Sink, Source};
-use std::collections::HashMap;
-use std::fs::File;
-use std::io::BufReader;
-use std::path::{Path, PathBuf};
+use crate::assets::Vfs;
+use crate::audio::acoustics::{self, Occlusion, RoomReverb};
+use crate::audio::buffer::SoundBuffer;
+use crate::audio::mixer::{Bus, Mixer};
+use crate::audio::voices::{SoundPriority, Voice, VoicePool};
+use glam::Vec2;
+use rodio::{OutputStreamHandle, Sink, Source};
+use std::collections::{HashMap, HashSet};
+
+const MAX_VOICES: usize = 24;
 
 pub struct SoundEffect {
     name: String,
-    path: PathBuf,
+    path: String, // Virtual path in the asset VFS
     volume: f32,
+    priority: SoundPriority,
+    max_instances: usize,
+    bus: Bus,
 }
 
 impl SoundEffect {
     pub fn new<S: Into<String>>(name: S, path: S) -> Self {
         Self {
             name: name.into(),
-            path: PathBuf::from(path.into()),
+            path: path.into(),
             volume: 1.0,
+            priority: SoundPriority::Normal,
+            max_instances: 4,
+            bus: Bus::Effects,
         }
     }
 
@@ -23,126 +34,187 @@ impl SoundEffect {
         self.volume = volume.clamp(0.0, 1.0);
         self
     }
+
+    pub fn with_priority(mut self, priority: SoundPriority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Route this sound through a mixer bus (effects by default)
+    pub fn with_bus(mut self, bus: Bus) -> Self {
+        self.bus = bus;
+        self
+    }
+
+    /// Limit how many copies of this sound may overlap
+    pub fn with_max_instances(mut self, max_instances: usize) -> Self {
+        self.max_instances = max_instances.max(1);
+        self
+    }
 }
 
 /// Manages sound effects and their playback
 pub struct SoundManager {
     stream_handle: OutputStreamHandle,
     effects: HashMap<String, SoundEffect>,
-    active_sinks: HashMap<String, Sink>,
-    master_volume: f32,
+    buffers: HashMap<String, SoundBuffer>,
+    unavailable: HashSet<String>,
+    voices: VoicePool,
+    mixer: Mixer,
+    map: Vec<Vec<i32>>,
+    listener: Vec2,
+    reverb: RoomReverb,
 }
 
 impl SoundManager {
-    pub fn new(stream_handle: OutputStreamHandle) -> Self {
+    pub fn new(stream_handle: OutputStreamHandle, mixer: Mixer) -> Self {
         Self {
             stream_handle,
             effects: HashMap::new(),
-            active_sinks: HashMap::new(),
-            master_volume: 1.0,
+            buffers: HashMap::new(),
+            unavailable: HashSet::new(),
+            voices: VoicePool::new(MAX_VOICES),
+            mixer,
+            map: Vec::new(),
+            listener: Vec2::ZERO,
+            reverb: RoomReverb::dry(),
         }
     }
 
-    pub fn register_sound<S: Into<String>>(&mut self, name: S, path: S) {
-        let effect = SoundEffect::new(name.into(), path.into());
-        self.effects.insert(effect.name.clone(), effect);
+    /// Set the map used for occlusion and reverb estimation
+    pub fn set_map(&mut self, map: Vec<Vec<i32>>) {
+        self.map = map;
+        self.update_reverb();
     }
 
-    pub fn play_sound(&mut self, name: &str) -> Result<(), String> {
-        let effect = self
-            .effects
-            .get(name)
-            .ok_or_else(|| format!("Sound effect '{}' not found", name))?;
-
-        // Create a new sink for this playback
-        let sink = Sink::try_new(&self.stream_handle)
-            .map_err(|e| format!("Failed to create audio sink: {}", e))?;
-
-        let file =
-            File::open(&effect.path).map_err(|e| format!("Failed to open audio file: {}", e))?;
-        let source = Decoder::new(BufReader::new(file))
-            .map_err(|e| format!("Failed to decode audio file: {}", e))?;
-
-        sink.set_volume(effect.volume * self.master_volume);
-        sink.append(source);
+    /// Move the listener, re-estimating room reverb if it changed cells. Called every
+    /// frame, so finished voices are let go here too.
+    pub fn set_listener(&mut self, position: Vec2) {
+        self.voices.reclaim();
+        let changed_cell = self.listener.floor() != position.floor();
+        self.listener = position;
+        if changed_cell {
+            self.update_reverb();
+        }
+    }
 
-        // Store the sink
-        self.active_sinks.insert(name.to_string(), sink);
+    fn update_reverb(&mut self) {
+        self.reverb = if self.map.is_empty() {
+            RoomReverb::dry()
+        } else {
+            acoustics::room_reverb(self.listener, &self.map)
+        };
+    }
 
+    /// Register an effect, decoding its file once so every playback shares the samples
+    pub fn register_effect(&mut self, vfs: &Vfs, effect: SoundEffect) -> Result<(), String> {
+        if !self.buffers.contains_key(&effect.path) {
+            let buffer = match SoundBuffer::load(vfs, &effect.path) {
+                Ok(buffer) => buffer,
+                Err(e) => {
+                    self.unavailable.insert(effect.name.clone());
+                    return Err(e);
+                }
+            };
+            self.buffers.insert(effect.path.clone(), buffer);
+        }
+        self.unavailable.remove(&effect.name);
+        self.effects.insert(effect.name.clone(), effect);
         Ok(())
     }
 
-    pub fn stop_sound(&mut self, name: &str) {
-        if let Some(sink) = self.active_sinks.remove(name) {
-            sink.stop();
-        }
+    /// Mark a sound whose file is missing. Playing it becomes a silent no-op
+    /// since the problem was already reported at startup.
+    pub fn mark_unavailable(&mut self, name: &str) {
+        self.unavailable.insert(name.to_string());
     }
 
-    pub fn set_master_volume(&mut self, volume: f32) {
-        self.master_volume = volume.clamp(0.0, 1.0);
-        for sink in self.active_sinks.values() {
-            sink.set_volume(self.master_volume);
-        }
+    /// Whether `name` was registered. Optional sound hooks check this first, so a
+    /// definition without the sound stays quiet instead of reporting it missing.
+    pub fn has_sound(&self, name: &str) -> bool {
+        self.effects.contains_key(name)
     }
 
-    pub fn cleanup(&mut self) {
-        // Stop and remove all active sinks
-        for sink in self.active_sinks.values() {
-            sink.stop();
-        }
-        self.active_sinks.clear();
+    /// Play a sound at the listener, e.g. the player's own footsteps and weapon
+    pub fn play_sound(&mut self, name: &str) -> Result<(), String> {
+        self.play(name, Occlusion::none())
     }
-}
 
-pub struct MusicPlayer {
-    stream_handle: OutputStreamHandle,
-    current_track: Option<Sink>,
-    volume: f32,
-}
+    /// Play a sound emitted somewhere in the world.
+    /// Walls between the source and the listener muffle and attenuate it.
+    pub fn play_sound_at(&mut self, name: &str, position: Vec2) -> Result<(), String> {
+        let occlusion = if self.map.is_empty() {
+            Occlusion::none()
+        } else {
+            acoustics::occlusion(position, self.listener, &self.map)
+        };
+        self.play(name, occlusion)
+    }
 
-impl MusicPlayer {
-    pub fn new(stream_handle: OutputStreamHandle) -> Self {
-        Self {
-            stream_handle,
-            current_track: None,
-            volume: 0.3,
+    fn play(&mut self, name: &str, occlusion: Occlusion) -> Result<(), String> {
+        if self.unavailable.contains(name) {
+            return Ok(());
         }
-    }
 
-    pub fn play_music<P: AsRef<Path>>(&mut self, path: P) -> Result<(), String> {
-        // Stop any currently playing music
-        if let Some(sink) = &self.current_track {
-            sink.stop();
+        let effect = self
+            .effects
+            .get(name)
+            .ok_or_else(|| format!("Sound effect '{}' not found", name))?;
+        let buffer = self
+            .buffers
+            .get(&effect.path)
+            .ok_or_else(|| format!("Sound effect '{}' has no decoded buffer", name))?;
+
+        if !self
+            .voices
+            .make_room(name, effect.priority, effect.max_instances)
+        {
+            // Every voice is busy with something more important
+            return Ok(());
         }
 
+        // Create a new sink for this playback
         let sink = Sink::try_new(&self.stream_handle)
             .map_err(|e| format!("Failed to create audio sink: {}", e))?;
 
-        // Load and decode the music file
-        let file = File::open(path).map_err(|e| format!("Failed to open music file: {}", e))?;
-        let source = Decoder::new(BufReader::new(file))
-            .map_err(|e| format!("Failed to decode music file: {}", e))?;
+        // Effect and occlusion volume live on the sink, bus and master gain on the source
+        sink.set_volume(effect.volume * occlusion.attenuation);
+        let source = buffer.source();
+        match occlusion.cutoff {
+            Some(freq) => {
+                let source = self.mixer.route(source.low_pass(freq), effect.bus);
+                append_with_reverb(&sink, source, self.reverb);
+            }
+            None => {
+                let source = self.mixer.route(source, effect.bus);
+                append_with_reverb(&sink, source, self.reverb);
+            }
+        }
 
-        let source = source.repeat_infinite();
+        self.voices.push(Voice::new(name, sink, effect.priority));
 
-        sink.set_volume(self.volume);
-        sink.append(source);
-
-        self.current_track = Some(sink);
         Ok(())
     }
 
-    pub fn stop_music(&mut self) {
-        if let Some(sink) = &self.current_track {
-            sink.stop();
-        }
-        self.current_track = None;
+    /// Stop every playing instance of a sound
+    pub fn stop_sound(&mut self, name: &str) {
+        self.voices.stop(name);
     }
 
-    pub fn set_volume(&mut self, volume: f32) {
-        self.volume = volume.clamp(0.0, 1.0);
-        if let Some(sink) = &self.current_track {
-            sink.set_volume(self.volume);
-        }
+    pub fn cleanup(&mut self) {
+        // Stop and remove all active voices
+        self.voices.stop_all();
+    }
+}
+
+/// Append a source to a sink, mixing in a delayed echo for the current room
+fn append_with_reverb<S>(sink: &Sink, source: S, reverb: RoomReverb)
+where
+    S: Source<Item = f32> + Send + 'static,
+{
+    if reverb.wet > 0.0 {
+        sink.append(source.buffered().reverb(reverb.delay, reverb.wet));
+    } else {
+        sink.append(source);
     }
 }
@@ -0,0 +1,159 @@
+use rodio::Sink;
+use std::time::Instant;
+
+/// Priority used when the voice pool is full and a playing sound has to be stolen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SoundPriority {
+    Low,
+    Normal,
+    High,
+}
+
+/// A single playing instance of a sound effect
+pub struct Voice {
+    pub name: String,
+    pub sink: Sink,
+    pub priority: SoundPriority,
+    started: Instant,
+}
+
+impl Voice {
+    pub fn new(name: &str, sink: Sink, priority: SoundPriority) -> Self {
+        Self {
+            name: name.to_string(),
+            sink,
+            priority,
+            started: Instant::now(),
+        }
+    }
+}
+
+/// Fixed-size pool of playing voices with per-sound instance caps
+pub struct VoicePool {
+    voices: Vec<Voice>,
+    max_voices: usize,
+}
+
+impl VoicePool {
+    pub fn new(max_voices: usize) -> Self {
+        Self {
+            voices: Vec::with_capacity(max_voices),
+            max_voices,
+        }
+    }
+
+    /// Drop voices whose sinks have finished playing
+    pub fn reclaim(&mut self) {
+        self.voices.retain(|voice| !voice.sink.empty());
+    }
+
+    /// Make room for a new instance of `name`. Returns false if every voice
+    /// is busy with something more important and the new sound should be skipped.
+    pub fn make_room(&mut self, name: &str, priority: SoundPriority, max_instances: usize) -> bool {
+        self.reclaim();
+
+        // Respect the per-sound cap by replacing the oldest instance of the same sound
+        let instances = self.voices.iter().filter(|v| v.name == name).count();
+        if instances >= max_instances.max(1) {
+            if let Some(index) = self.oldest(|v| v.name == name) {
+                self.steal(index);
+            }
+        }
+
+        if self.voices.len() < self.max_voices {
+            return true;
+        }
+
+        // Pool is full: steal the oldest voice with the lowest priority below ours
+        let lowest = self.voices.iter().map(|v| v.priority).min();
+        match lowest {
+            Some(lowest) if lowest < priority => {
+                if let Some(index) = self.oldest(|v| v.priority == lowest) {
+                    self.steal(index);
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn push(&mut self, voice: Voice) {
+        self.voices.push(voice);
+    }
+
+    /// Stop every playing instance of `name`
+    pub fn stop(&mut self, name: &str) {
+        self.voices.retain(|voice| {
+            if voice.name == name {
+                voice.sink.stop();
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    pub fn stop_all(&mut self) {
+        for voice in &self.voices {
+            voice.sink.stop();
+        }
+        self.voices.clear();
+    }
+
+    fn oldest<F: Fn(&Voice) -> bool>(&self, filter: F) -> Option<usize> {
+        self.voices
+            .iter()
+            .enumerate()
+            .filter(|(_, voice)| filter(voice))
+            .min_by_key(|(_, voice)| voice.started)
+            .map(|(index, _)| index)
+    }
+
+    fn steal(&mut self, index: usize) {
+        let voice = self.voices.swap_remove(index);
+        voice.sink.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::source::Zero;
+
+    fn voice(name: &str, priority: SoundPriority) -> Voice {
+        // An idle sink with an endless silent source plays until stopped
+        let (sink, _output) = Sink::new_idle();
+        sink.append(Zero::<f32>::new(1, 44100));
+        Voice::new(name, sink, priority)
+    }
+
+    fn names(pool: &VoicePool) -> Vec<&str> {
+        let mut names: Vec<&str> = pool.voices.iter().map(|v| v.name.as_str()).collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_make_room_steals_lower_priority_and_caps_instances() {
+        let mut pool = VoicePool::new(2);
+        pool.push(voice("ambience", SoundPriority::Low));
+        pool.push(voice("shot", SoundPriority::Normal));
+
+        // A full pool gives up its lowest priority voice for a more important one
+        assert!(pool.make_room("scream", SoundPriority::High, 4));
+        assert_eq!(names(&pool), vec!["shot"]);
+        pool.push(voice("scream", SoundPriority::High));
+
+        // Nothing plays below Normal now, so Normal and Low sounds are skipped
+        assert!(!pool.make_room("step", SoundPriority::Normal, 4));
+        assert!(!pool.make_room("step", SoundPriority::Low, 4));
+        assert_eq!(names(&pool), vec!["scream", "shot"]);
+
+        // A sound at its instance cap replaces its own oldest instance instead
+        assert!(pool.make_room("shot", SoundPriority::Normal, 1));
+        assert_eq!(names(&pool), vec!["scream"]);
+
+        pool.stop_all();
+        assert!(pool.voices.is_empty());
+    }
+}
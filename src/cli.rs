@@ -0,0 +1,97 @@
+use crate::assets::{pak, Vfs};
+use crate::game::maps::lint::{self, Severity};
+use crate::game::maps::MapFile;
+use crate::game::wall_textures::WallTextures;
+use crate::mods::{ModList, MODS_DIR};
+use anyhow::Result;
+use std::path::Path;
+
+const USAGE: &str = "Usage:
+  lucid-raycaster                                   Run the game
+  lucid-raycaster pack <root> <output.pak> [dirs...]  Pack files under <root> into an archive
+  lucid-raycaster map-lint <map.toml>...            Check maps and exit non-zero on errors";
+
+/// Run a tool subcommand if one was given. Returns None to start the game normally.
+pub fn run(args: &[String]) -> Option<Result<()>> {
+    let command = args.first()?;
+    let rest = &args[1..];
+
+    let result = match command.as_str() {
+        "pack" => pack(rest),
+        "map-lint" => map_lint(rest),
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        _ => Err(anyhow::anyhow!("Unknown command '{}'\n{}", command, USAGE)),
+    };
+    Some(result)
+}
+
+fn pack(args: &[String]) -> Result<()> {
+    let (root, output) = match args {
+        [root, output, ..] => (Path::new(root), Path::new(output)),
+        _ => anyhow::bail!("pack needs a root directory and an output file\n{}", USAGE),
+    };
+
+    let count = pak::build_archive(root, &args[2..], output).map_err(anyhow::Error::msg)?;
+    println!("Packed {} files into {}", count, output.display());
+    Ok(())
+}
+
+fn map_lint(paths: &[String]) -> Result<()> {
+    if paths.is_empty() {
+        anyhow::bail!("map-lint needs at least one map file\n{}", USAGE);
+    }
+
+    // Wall textures come from the game data, wherever the maps being checked are
+    let mut vfs = Vfs::with_base_data();
+    let mods_dir = vfs.base_dir().unwrap_or(Path::new(".")).join(MODS_DIR);
+    ModList::discover(mods_dir).mount(&mut vfs);
+    lint_maps(&vfs, paths)
+}
+
+fn lint_maps(vfs: &Vfs, paths: &[String]) -> Result<()> {
+    let wall_textures = WallTextures::load_from(vfs)?.len();
+    let mut errors = 0;
+    for path in paths {
+        let map_file = match MapFile::load(path) {
+            Ok(map_file) => map_file,
+            Err(e) => {
+                println!("{}: error: {}", path, e);
+                errors += 1;
+                continue;
+            }
+        };
+
+        let diagnostics = lint::lint(&map_file, wall_textures);
+        for diagnostic in &diagnostics {
+            println!("{}: {}", path, diagnostic);
+        }
+        errors += diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+            .count();
+    }
+
+    if errors > 0 {
+        anyhow::bail!("{} errors found", errors);
+    }
+    println!("Checked {} maps, no errors", paths.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_lint_reads_wall_textures_through_the_vfs() {
+        // Mounting the data directory explicitly, the working directory doesn't matter
+        let data = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let mut vfs = Vfs::new();
+        vfs.mount_dir(data, 0);
+        let map = data.join("assets/maps/test.toml");
+        assert!(lint_maps(&vfs, &[map.to_string_lossy().into_owned()]).is_ok());
+    }
+}
@@ -0,0 +1,114 @@
+use crate::assets::Vfs;
+use crate::engine::texture::Filter;
+use anyhow::Result;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AudioConfig {
+    pub master_volume: f32,
+    pub music_volume: f32,
+    pub effects_volume: f32,
+    pub weapon_volume: f32,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        Self {
+            master_volume: 1.0,
+            music_volume: 0.7,
+            effects_volume: 0.8,
+            weapon_volume: 0.9,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TextureQuality {
+    High,
+    Medium,
+    Low,
+}
+
+impl TextureQuality {
+    /// How many of the largest mip levels are skipped
+    pub fn base_mip(self) -> usize {
+        match self {
+            TextureQuality::High => 0,
+            TextureQuality::Medium => 1,
+            TextureQuality::Low => 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct GraphicsConfig {
+    pub texture_quality: TextureQuality,
+    pub texture_filter: Filter, // nearest, bilinear or trilinear
+    pub draw_distance: f32,     // Map units; rays stop and fade into fog past this
+    pub max_reflections: u32,   // Mirror bounces per ray
+}
+
+impl Default for GraphicsConfig {
+    fn default() -> Self {
+        Self {
+            texture_quality: TextureQuality::High,
+            texture_filter: Filter::default(),
+            draw_distance: 20.0,
+            max_reflections: 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct GameplayConfig {
+    pub map: String, // Grid map or sector map to start on
+}
+
+impl Default for GameplayConfig {
+    fn default() -> Self {
+        Self {
+            map: "assets/maps/test.toml".to_string(),
+        }
+    }
+}
+
+/// Game configuration loaded from `config/default.toml` through the VFS.
+/// Missing sections and keys fall back to their defaults.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub audio: AudioConfig,
+    pub graphics: GraphicsConfig,
+    pub gameplay: GameplayConfig,
+}
+
+impl Config {
+    pub fn load(vfs: &Vfs, path: &str) -> Result<Self> {
+        let content = vfs.read_to_string(path).map_err(anyhow::Error::msg)?;
+        let config: Config = toml::from_str(&content)?;
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_default_config() {
+        let mut vfs = Vfs::new();
+        vfs.mount_dir(".", 0);
+        let config = Config::load(&vfs, "config/default.toml").unwrap();
+        assert_eq!(config.audio.music_volume, 0.7);
+        assert_eq!(config.audio.weapon_volume, 0.9);
+        assert_eq!(config.graphics.draw_distance, 20.0);
+        assert_eq!(config.graphics.max_reflections, 2);
+        assert_eq!(config.graphics.texture_quality.base_mip(), 0);
+        assert_eq!(config.graphics.texture_filter, Filter::Bilinear);
+        assert_eq!(config.gameplay.map, "assets/maps/test.toml");
+    }
+}
@@ -1,9 +1,27 @@
+use crate::engine::fold::Fold;
+use crate::engine::heights::HeightMap;
+use crate::engine::thin_walls::ThinWalls;
 use glam::Vec2;
 
+pub const EYE_HEIGHT: f32 = 0.5; // Eye level above the floor
+pub const CROUCH_EYE_HEIGHT: f32 = 0.25;
+pub const HEAD_ROOM: f32 = 0.1; // Space kept between the eye and the ceiling
+pub const STEP_HEIGHT: f32 = 0.35; // Tallest ledge the player can walk up
+const CLIMB_SPEED: f32 = 4.0; // How fast the eye catches up after a step or crouch, in units per second
+const GRAVITY: f32 = 12.0; // Units per second squared
+const JUMP_SPEED: f32 = 3.0; // Units per second, about a third of a unit high
+const MAX_PITCH: f32 = 0.5; // Horizon offset limit, as a fraction of screen height
+
 pub struct Camera {
     pub position: Vec2,
     pub direction: Vec2,
     pub plane: Vec2,
+    pub z: f32,     // Eye height in world units
+    pub pitch: f32, // Horizon offset as a fraction of screen height, positive looks up
+    pub vertical_velocity: f32,
+    pub on_ground: bool,
+    pub crouching: bool,
+    crouch_held: bool,
 }
 
 impl Camera {
@@ -12,6 +30,12 @@ impl Camera {
             position: Vec2::new(x, y),
             direction: Vec2::new(1.0, 0.0),
             plane: Vec2::new(0.0, -0.8), // FOV of about ~80 degrees
+            z: EYE_HEIGHT,
+            pitch: 0.0,
+            vertical_velocity: 0.0,
+            on_ground: true,
+            crouching: false,
+            crouch_held: false,
         }
     }
 
@@ -27,18 +51,116 @@ impl Camera {
         self.plane.y = old_plane_x * angle.sin() + self.plane.y * angle.cos();
     }
 
-    pub fn move_forward(&mut self, speed: f32, map: &[Vec<i32>]) {
+    pub fn move_forward(
+        &mut self,
+        speed: f32,
+        map: &[Vec<i32>],
+        heights: &HeightMap,
+        walls: &ThinWalls,
+    ) {
         let move_vec = self.direction * speed;
-        self.move_with_collision(move_vec, map);
+        self.move_with_collision(move_vec, map, heights, walls);
     }
 
-    pub fn move_right(&mut self, speed: f32, map: &[Vec<i32>]) {
+    pub fn move_right(
+        &mut self,
+        speed: f32,
+        map: &[Vec<i32>],
+        heights: &HeightMap,
+        walls: &ThinWalls,
+    ) {
         let right = Vec2::new(self.direction.y, -self.direction.x);
         let move_vec = right * speed;
-        self.move_with_collision(move_vec, map);
+        self.move_with_collision(move_vec, map, heights, walls);
+    }
+
+    /// Shove the camera, e.g. knocked back by a blow; walls still stop it
+    pub fn push(&mut self, offset: Vec2, map: &[Vec<i32>], heights: &HeightMap, walls: &ThinWalls) {
+        self.move_with_collision(offset, map, heights, walls);
+    }
+
+    /// Come out of a portal: move to the linked spot and turn with it
+    pub fn travel(&mut self, fold: Fold) {
+        self.position = fold.point(self.position);
+        self.direction = fold.direction(self.direction);
+        self.plane = fold.direction(self.plane);
+    }
+
+    /// Look up or down by shearing the view; there is no real vertical rotation
+    pub fn look_up(&mut self, amount: f32) {
+        self.pitch = (self.pitch + amount).clamp(-MAX_PITCH, MAX_PITCH);
+    }
+
+    pub fn jump(&mut self) {
+        if self.on_ground {
+            self.vertical_velocity = JUMP_SPEED;
+            self.on_ground = false;
+        }
+    }
+
+    pub fn set_crouching(&mut self, crouching: bool) {
+        self.crouch_held = crouching;
+        if crouching {
+            self.crouching = true;
+        }
+    }
+
+    pub fn eye_height(&self) -> f32 {
+        if self.crouching {
+            CROUCH_EYE_HEIGHT
+        } else {
+            EYE_HEIGHT
+        }
+    }
+
+    /// Height of the camera's feet; lags behind the floor while climbing stairs
+    pub fn feet(&self) -> f32 {
+        self.z - self.eye_height()
+    }
+
+    /// Apply gravity and ease the eye toward standing height over the floor,
+    /// so stairs, ledges and crouching feel smooth
+    pub fn update_height(&mut self, heights: &HeightMap, dt: f32) {
+        let floor = heights.floor_at(self.position);
+        let ceiling = heights.ceiling_at(self.position);
+
+        // Only stand back up once there's room overhead
+        if self.crouching && !self.crouch_held {
+            self.crouching = ceiling - floor < EYE_HEIGHT + HEAD_ROOM;
+        }
+        let standing = floor + self.eye_height();
+
+        // Walking off a ledge taller than a step means falling
+        if self.on_ground && self.z - standing > STEP_HEIGHT {
+            self.on_ground = false;
+            self.vertical_velocity = 0.0;
+        }
+
+        if self.on_ground {
+            let max_step = CLIMB_SPEED * dt;
+            self.z += (standing - self.z).clamp(-max_step, max_step);
+        } else {
+            self.vertical_velocity -= GRAVITY * dt;
+            self.z += self.vertical_velocity * dt;
+            if self.z > ceiling - HEAD_ROOM {
+                self.z = ceiling - HEAD_ROOM;
+                self.vertical_velocity = self.vertical_velocity.min(0.0);
+            }
+            if self.z <= standing {
+                self.z = standing;
+                self.vertical_velocity = 0.0;
+                self.on_ground = true;
+            }
+        }
     }
 
-    fn move_with_collision(&mut self, move_vec: Vec2, map: &[Vec<i32>]) {
+    fn move_with_collision(
+        &mut self,
+        move_vec: Vec2,
+        map: &[Vec<i32>],
+        heights: &HeightMap,
+        walls: &ThinWalls,
+    ) {
         const COLLISION_BUFFER: f32 = 0.3;
         let new_pos = self.position + move_vec;
 
@@ -57,15 +179,33 @@ impl Camera {
 
         // Try to move in each direction independently
         let mut next_pos = self.position;
-        if !self.check_collision(Vec2::new(new_pos.x, self.position.y), map) || moving_away_x {
+        let step_x = Vec2::new(new_pos.x, self.position.y);
+        if (!self.check_collision(step_x, map) || moving_away_x)
+            && self.can_step(step_x, heights)
+            && !walls.blocks_move(self.position, step_x, COLLISION_BUFFER)
+        {
             next_pos.x = new_pos.x;
         }
-        if !self.check_collision(Vec2::new(next_pos.x, new_pos.y), map) || moving_away_y {
+        let step_y = Vec2::new(next_pos.x, new_pos.y);
+        if (!self.check_collision(step_y, map) || moving_away_y)
+            && self.can_step(step_y, heights)
+            && !walls.blocks_move(next_pos, step_y, COLLISION_BUFFER)
+        {
             next_pos.y = new_pos.y;
         }
         self.position = next_pos;
     }
 
+    /// Ledges taller than STEP_HEIGHT and ceilings lower than eye level block movement.
+    /// Jumping raises the feet, so higher ledges can be reached from the air
+    fn can_step(&self, pos: Vec2, heights: &HeightMap) -> bool {
+        let current = heights.floor_at(self.position).max(self.feet());
+        let floor = heights.floor_at(pos);
+        let ceiling = heights.ceiling_at(pos);
+        floor - current <= STEP_HEIGHT
+            && ceiling - floor.max(current) >= self.eye_height() + HEAD_ROOM
+    }
+
     fn check_collision(&self, pos: Vec2, map: &[Vec<i32>]) -> bool {
         const COLLISION_BUFFER: f32 = 0.3;
 
@@ -119,3 +259,25 @@ impl Camera {
         self.check_collision(check_pos, map)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jump_lands_back_on_floor() {
+        let heights = HeightMap::flat(3, 3);
+        let mut camera = Camera::new(1.5, 1.5);
+        camera.jump();
+        assert!(!camera.on_ground);
+
+        let mut peak: f32 = 0.0;
+        for _ in 0..120 {
+            camera.update_height(&heights, 1.0 / 60.0);
+            peak = peak.max(camera.z);
+        }
+        assert!(peak > EYE_HEIGHT + 0.2);
+        assert!(camera.on_ground);
+        assert_eq!(camera.z, EYE_HEIGHT);
+    }
+}
@@ -0,0 +1,113 @@
+use glam::{Mat2, Vec2};
+
+/// Maps the straight ray's "virtual" space past mirrors and window portals back onto
+/// the real map. Both only ever flip or quarter-turn the grid about whole or half
+/// coordinates, so cells always land on cells.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fold {
+    matrix: Mat2, // Only 0 and ±1 entries
+    offset: Vec2,
+}
+
+impl Fold {
+    pub const IDENTITY: Fold = Fold {
+        matrix: Mat2::IDENTITY,
+        offset: Vec2::ZERO,
+    };
+
+    /// Turn by `turns` quarter turns (+x towards +y) about the center of cell `from`,
+    /// then move that center onto the center of cell `to`
+    pub fn turn(turns: u8, from: Vec2, to: Vec2) -> Self {
+        let matrix = match turns % 4 {
+            0 => Mat2::IDENTITY,
+            1 => Mat2::from_cols(Vec2::Y, -Vec2::X),
+            2 => -Mat2::IDENTITY,
+            _ => Mat2::from_cols(-Vec2::Y, Vec2::X),
+        };
+        let (from, to) = (from + 0.5, to + 0.5);
+        Self {
+            matrix,
+            offset: to - matrix * from,
+        }
+    }
+
+    /// Add a mirror along the line where virtual coordinate `axis` (0 = x, 1 = y) equals `at`
+    pub fn reflect(self, axis: usize, at: f32) -> Self {
+        let mut flip = Mat2::IDENTITY;
+        let mut across = Vec2::ZERO;
+        flip.col_mut(axis)[axis] = -1.0;
+        across[axis] = 2.0 * at;
+        Self {
+            matrix: self.matrix * flip,
+            offset: self.offset + self.matrix * across,
+        }
+    }
+
+    /// This fold followed by `next`, for a ray that meets another portal further on
+    pub fn then(self, next: Fold) -> Self {
+        Self {
+            matrix: next.matrix * self.matrix,
+            offset: next.matrix * self.offset + next.offset,
+        }
+    }
+
+    pub fn point(&self, point: Vec2) -> Vec2 {
+        self.matrix * point + self.offset
+    }
+
+    pub fn direction(&self, direction: Vec2) -> Vec2 {
+        self.matrix * direction
+    }
+
+    /// The real cell a virtual cell (given by its corner) lands on
+    pub fn cell(&self, cell: Vec2) -> Vec2 {
+        self.point(cell + 0.5).floor()
+    }
+
+    /// The real axis a virtual axis runs along, for side shading and texture coordinates
+    pub fn axis(&self, axis: usize) -> usize {
+        if self.matrix.col(axis).x != 0.0 {
+            0
+        } else {
+            1
+        }
+    }
+
+    /// Where something at a real position appears in the virtual space
+    pub fn unfold(&self, point: Vec2) -> Vec2 {
+        // The matrix is orthogonal, so its transpose undoes it
+        self.matrix.transpose() * (point - self.offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fold_maps_virtual_cells_back() {
+        // Mirror on the face x = 5; virtual cell 5 is real cell 4, virtual 7 is real 2
+        let fold = Fold::IDENTITY.reflect(0, 5.0);
+        assert_eq!(fold.cell(Vec2::new(5.0, 3.0)), Vec2::new(4.0, 3.0));
+        assert_eq!(fold.cell(Vec2::new(7.0, 3.0)), Vec2::new(2.0, 3.0));
+        assert_eq!(fold.direction(Vec2::new(1.0, 1.0)), Vec2::new(-1.0, 1.0));
+
+        let point = Vec2::new(3.25, 1.5);
+        assert_eq!(fold.point(fold.unfold(point)), point);
+
+        // A second bounce on y = 0 flips the other axis too
+        let twice = fold.reflect(1, 0.0);
+        assert_eq!(twice.cell(Vec2::new(6.0, -1.0)), Vec2::new(3.0, 0.0));
+
+        // A portal from cell (1, 1) to (4, 2), turning a quarter: heading +x comes out heading +y
+        let portal = Fold::turn(1, Vec2::new(1.0, 1.0), Vec2::new(4.0, 2.0));
+        assert_eq!(portal.cell(Vec2::new(1.0, 1.0)), Vec2::new(4.0, 2.0));
+        assert_eq!(portal.cell(Vec2::new(2.0, 1.0)), Vec2::new(4.0, 3.0));
+        assert_eq!(portal.direction(Vec2::X), Vec2::Y);
+        assert_eq!(portal.axis(0), 1);
+        assert_eq!(
+            Fold::IDENTITY.then(portal).point(point),
+            portal.point(point)
+        );
+    }
+}
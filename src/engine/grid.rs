@@ -0,0 +1,61 @@
+use glam::Vec2;
+
+/// Returns true if the map cell under `point` is a wall or outside the grid
+pub fn is_blocked(point: Vec2, map: &[Vec<i32>]) -> bool {
+    if point.x < 0.0 || point.y < 0.0 {
+        return true;
+    }
+
+    let x = point.x.floor() as usize;
+    let y = point.y.floor() as usize;
+    x >= map[0].len() || y >= map.len() || map[y][x] != 0
+}
+
+/// Count the distinct wall cells crossed by a straight line between two points.
+/// The line is sampled every half unit, the same way enemies check line of sight.
+pub fn walls_between(start: Vec2, end: Vec2, map: &[Vec<i32>]) -> usize {
+    let distance = (end - start).length();
+    if distance <= f32::EPSILON {
+        return usize::from(is_blocked(start, map));
+    }
+
+    let direction = (end - start) / distance;
+    let steps = ((distance * 2.0) as usize).max(1);
+    let step_size = distance / steps as f32;
+
+    let mut walls = 0;
+    let mut last_cell = None;
+    for i in 0..=steps {
+        let point = start + direction * (i as f32 * step_size);
+        let cell = (point.x.floor() as i32, point.y.floor() as i32);
+
+        if is_blocked(point, map) && last_cell != Some(cell) {
+            walls += 1;
+        }
+        last_cell = Some(cell);
+    }
+
+    walls
+}
+
+/// Check whether a straight line between two points is free of walls
+pub fn line_of_sight(start: Vec2, end: Vec2, map: &[Vec<i32>]) -> bool {
+    walls_between(start, end, map) == 0
+}
+
+/// March from `start` along `direction` until a wall is hit or `max_distance` is reached.
+/// Returns the distance travelled.
+pub fn free_distance(start: Vec2, direction: Vec2, max_distance: f32, map: &[Vec<i32>]) -> f32 {
+    const STEP: f32 = 0.25;
+
+    let mut travelled = 0.0;
+    while travelled < max_distance {
+        let next = travelled + STEP;
+        if is_blocked(start + direction * next, map) {
+            break;
+        }
+        travelled = next;
+    }
+
+    travelled.min(max_distance)
+}
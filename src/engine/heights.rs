@@ -0,0 +1,116 @@
+use glam::Vec2;
+
+pub const DEFAULT_WALL_HEIGHT: f32 = 1.0;
+pub const DEFAULT_ROOM_HEIGHT: f32 = 1.0; // Ceiling above the floor when no ceiling layer is given
+
+/// Per-cell height layers. Floors and ceilings apply to empty cells;
+/// walls rise `wall` units from their cell's floor. Outdoor cells have an infinite ceiling.
+#[derive(Debug, Clone)]
+pub struct HeightMap {
+    width: usize,
+    height: usize,
+    floor: Vec<f32>,
+    ceiling: Vec<f32>,
+    wall: Vec<f32>,
+}
+
+impl HeightMap {
+    /// Every floor at 0, every ceiling and wall one unit up
+    pub fn flat(width: usize, height: usize) -> Self {
+        Self::from_layers(width, height, None, None, None)
+    }
+
+    pub fn from_layers(
+        width: usize,
+        height: usize,
+        floor: Option<&[Vec<f32>]>,
+        ceiling: Option<&[Vec<f32>]>,
+        wall: Option<&[Vec<f32>]>,
+    ) -> Self {
+        let flatten = |layer: Option<&[Vec<f32>]>, default: f32| -> Vec<f32> {
+            match layer {
+                Some(rows) => rows.iter().flatten().copied().collect(),
+                None => vec![default; width * height],
+            }
+        };
+
+        let floor = flatten(floor, 0.0);
+        let ceiling = match ceiling {
+            Some(_) => flatten(ceiling, 0.0),
+            None => floor.iter().map(|f| f + DEFAULT_ROOM_HEIGHT).collect(),
+        };
+        let wall = flatten(wall, DEFAULT_WALL_HEIGHT);
+
+        Self {
+            width,
+            height,
+            floor,
+            ceiling,
+            wall,
+        }
+    }
+
+    /// Cells marked non-zero in `outdoor` have no ceiling and show the sky instead
+    pub fn with_sky(mut self, outdoor: Option<&[Vec<u8>]>) -> Self {
+        let cells = outdoor.into_iter().flatten().flatten();
+        for (ceiling, &open) in self.ceiling.iter_mut().zip(cells) {
+            if open != 0 {
+                *ceiling = f32::INFINITY;
+            }
+        }
+        self
+    }
+
+    pub fn is_outdoor(&self, x: i32, y: i32) -> bool {
+        self.ceiling(x, y).is_infinite()
+    }
+
+    fn index(&self, x: i32, y: i32) -> Option<usize> {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return None;
+        }
+        Some(y as usize * self.width + x as usize)
+    }
+
+    pub fn floor(&self, x: i32, y: i32) -> f32 {
+        self.index(x, y).map_or(0.0, |i| self.floor[i])
+    }
+
+    pub fn ceiling(&self, x: i32, y: i32) -> f32 {
+        self.index(x, y)
+            .map_or(DEFAULT_ROOM_HEIGHT, |i| self.ceiling[i])
+    }
+
+    pub fn wall(&self, x: i32, y: i32) -> f32 {
+        self.index(x, y)
+            .map_or(DEFAULT_WALL_HEIGHT, |i| self.wall[i])
+    }
+
+    pub fn floor_at(&self, point: Vec2) -> f32 {
+        self.floor(point.x.floor() as i32, point.y.floor() as i32)
+    }
+
+    pub fn ceiling_at(&self, point: Vec2) -> f32 {
+        self.ceiling(point.x.floor() as i32, point.y.floor() as i32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ceiling_defaults_above_floor() {
+        let floor = vec![vec![0.0, 0.25], vec![0.5, 0.0]];
+        let heights = HeightMap::from_layers(2, 2, Some(&floor), None, None);
+        assert_eq!(heights.floor(1, 0), 0.25);
+        assert_eq!(heights.ceiling(0, 1), 1.5);
+        assert_eq!(heights.wall(1, 1), DEFAULT_WALL_HEIGHT);
+        assert_eq!(heights.floor(5, 5), 0.0);
+
+        let outdoor = vec![vec![0, 1], vec![0, 0]];
+        let heights = heights.with_sky(Some(&outdoor));
+        assert!(heights.is_outdoor(1, 0));
+        assert!(!heights.is_outdoor(0, 0));
+    }
+}
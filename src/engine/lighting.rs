@@ -0,0 +1,233 @@
+use crate::engine::grid;
+use glam::Vec2;
+
+// Lights may push surfaces past their texture color, up to this much
+const MAX_BRIGHTNESS: f32 = 1.6;
+
+/// How quickly surfaces fade into the fog color with distance
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fog {
+    Linear { start: f32, end: f32 },
+    Exponential { density: f32 },
+}
+
+impl Fog {
+    /// 0.0 = no fog, 1.0 = fully fogged
+    pub fn factor(&self, distance: f32) -> f32 {
+        match *self {
+            Fog::Linear { start, end } => {
+                if end <= start {
+                    return if distance >= end { 1.0 } else { 0.0 };
+                }
+                ((distance - start) / (end - start)).clamp(0.0, 1.0)
+            }
+            Fog::Exponential { density } => 1.0 - (-density * distance.max(0.0)).exp(),
+        }
+    }
+}
+
+/// Per-map lighting shared by walls, floors, sprites and particles
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Lighting {
+    pub ambient: f32, // Base light level for every surface
+    pub fog_color: [u8; 3],
+    pub fog: Fog,
+    pub side_shade: f32, // Extra darkening for walls facing north/south
+}
+
+impl Default for Lighting {
+    fn default() -> Self {
+        Self {
+            ambient: 1.0,
+            fog_color: [0, 0, 0],
+            fog: Fog::Exponential { density: 0.08 },
+            side_shade: 0.7,
+        }
+    }
+}
+
+impl Lighting {
+    /// Light level of a surface given the light from the light map and dynamic lights
+    pub fn light_level(&self, extra: f32) -> f32 {
+        self.ambient + extra
+    }
+
+    /// Scale a color by `light` (see `light_level`) and blend it into the fog
+    pub fn shade(&self, rgb: [u8; 3], distance: f32, light: f32) -> [u8; 3] {
+        let brightness = light.clamp(0.0, MAX_BRIGHTNESS);
+        let fog = self.fog.factor(distance);
+
+        let mut out = [0u8; 3];
+        for i in 0..3 {
+            let lit = (rgb[i] as f32 * brightness).min(255.0);
+            out[i] = (lit + (self.fog_color[i] as f32 - lit) * fog) as u8;
+        }
+        out
+    }
+
+    /// Fog only, for things that give off their own light like projectiles
+    pub fn shade_emissive(&self, rgb: [u8; 3], distance: f32) -> [u8; 3] {
+        let fog = self.fog.factor(distance);
+
+        let mut out = [0u8; 3];
+        for i in 0..3 {
+            let c = rgb[i] as f32;
+            out[i] = (c + (self.fog_color[i] as f32 - c) * fog) as u8;
+        }
+        out
+    }
+}
+
+/// A point light with linear-squared falloff out to `radius`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PointLight {
+    pub position: Vec2,
+    pub radius: f32,
+    pub intensity: f32,
+}
+
+impl PointLight {
+    pub fn new(position: Vec2, radius: f32, intensity: f32) -> Self {
+        Self {
+            position,
+            radius,
+            intensity,
+        }
+    }
+
+    /// Light reaching `point`, ignoring walls
+    pub fn contribution(&self, point: Vec2) -> f32 {
+        if self.radius <= 0.0 {
+            return 0.0;
+        }
+        let falloff = (1.0 - point.distance(self.position) / self.radius).max(0.0);
+        self.intensity * falloff * falloff
+    }
+}
+
+/// A short-lived light such as a muzzle flash or an explosion
+#[derive(Debug, Clone, Copy)]
+pub struct DynamicLight {
+    pub light: PointLight,
+    pub remaining: f32, // Seconds
+    duration: f32,
+}
+
+impl DynamicLight {
+    pub fn new(light: PointLight, duration: f32) -> Self {
+        Self {
+            light,
+            remaining: duration,
+            duration,
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.remaining -= dt;
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.remaining > 0.0
+    }
+
+    /// The light as it is this frame; it fades out over its lifetime
+    pub fn current(&self) -> PointLight {
+        let fade = (self.remaining / self.duration).clamp(0.0, 1.0);
+        PointLight {
+            intensity: self.light.intensity * fade,
+            ..self.light
+        }
+    }
+}
+
+/// Static light per map cell, baked at load from the map's light sources.
+/// Walls block light; each empty cell is lit from its center.
+pub struct LightMap {
+    width: usize,
+    height: usize,
+    cells: Vec<f32>,
+}
+
+impl LightMap {
+    pub fn empty() -> Self {
+        Self {
+            width: 0,
+            height: 0,
+            cells: Vec::new(),
+        }
+    }
+
+    pub fn build(map: &[Vec<i32>], lights: &[PointLight]) -> Self {
+        let height = map.len();
+        let width = map.first().map_or(0, |row| row.len());
+        let mut cells = vec![0.0; width * height];
+
+        for (y, row) in map.iter().enumerate() {
+            for (x, &tile) in row.iter().enumerate() {
+                if tile != 0 {
+                    continue;
+                }
+                let center = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+                cells[y * width + x] = lights
+                    .iter()
+                    .filter(|light| center.distance(light.position) < light.radius)
+                    .filter(|light| grid::line_of_sight(light.position, center, map))
+                    .map(|light| light.contribution(center))
+                    .sum();
+            }
+        }
+
+        Self {
+            width,
+            height,
+            cells,
+        }
+    }
+
+    pub fn get(&self, x: i32, y: i32) -> f32 {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return 0.0;
+        }
+        self.cells[y as usize * self.width + x as usize]
+    }
+
+    pub fn sample(&self, point: Vec2) -> f32 {
+        self.get(point.x.floor() as i32, point.y.floor() as i32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_linear_fog_blends_to_fog_color() {
+        let lighting = Lighting {
+            ambient: 0.5,
+            fog_color: [100, 0, 0],
+            fog: Fog::Linear {
+                start: 2.0,
+                end: 6.0,
+            },
+            side_shade: 0.7,
+        };
+        let light = lighting.light_level(0.0);
+        assert_eq!(lighting.shade([200, 200, 200], 1.0, light), [100, 100, 100]);
+        assert_eq!(lighting.shade([200, 200, 200], 4.0, light), [100, 50, 50]);
+        assert_eq!(lighting.shade([200, 200, 200], 10.0, light), [100, 0, 0]);
+    }
+
+    #[test]
+    fn test_light_map_is_blocked_by_walls() {
+        let map = vec![
+            vec![1, 1, 1, 1, 1],
+            vec![1, 0, 1, 0, 1],
+            vec![1, 1, 1, 1, 1],
+        ];
+        let light = PointLight::new(Vec2::new(1.5, 1.5), 4.0, 1.0);
+        let light_map = LightMap::build(&map, &[light]);
+        assert_eq!(light_map.get(1, 1), 1.0);
+        assert_eq!(light_map.get(3, 1), 0.0);
+        assert_eq!(light_map.get(-1, 0), 0.0);
+    }
+}
@@ -0,0 +1,32 @@
+/// A reflective wall type. What shows in it is blended towards `tint` by `strength`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mirror {
+    pub tint: [u8; 3],
+    pub strength: f32,
+}
+
+impl Mirror {
+    pub fn apply(&self, rgb: [u8; 3]) -> [u8; 3] {
+        let strength = self.strength.clamp(0.0, 1.0);
+        let mut out = [0u8; 3];
+        for i in 0..3 {
+            let c = rgb[i] as f32;
+            out[i] = (c + (self.tint[i] as f32 - c) * strength) as u8;
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mirror_tint() {
+        let mirror = Mirror {
+            tint: [0, 0, 255],
+            strength: 0.5,
+        };
+        assert_eq!(mirror.apply([200, 100, 0]), [100, 50, 127]);
+    }
+}
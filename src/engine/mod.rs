@@ -1,6 +1,17 @@
 pub mod camera;
+pub mod fold;
+pub mod font;
+pub mod grid;
+pub mod heights;
+pub mod lighting;
+pub mod mirrors;
+pub mod portals;
 pub mod raycaster;
+pub mod sectors;
+pub mod sky;
+pub mod sprite;
 pub mod texture;
+pub mod thin_walls;
 
 pub use camera::Camera;
 pub use raycaster::Raycaster;
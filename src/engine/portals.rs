@@ -0,0 +1,146 @@
+use crate::engine::fold::Fold;
+use crate::engine::grid;
+use glam::Vec2;
+
+// Portal checks along a line sample it this often, in map units
+const SAMPLE_STEP: f32 = 0.1;
+
+/// A cell linked to another. Anything that enters `from` comes out at the same spot
+/// of `to`, turned by `turns` quarter turns. Through a window portal, rays carry on
+/// from `to` as well, so the destination shows through seamlessly.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Portal {
+    pub from: (i32, i32),
+    pub to: (i32, i32),
+    pub turns: u8,
+    pub window: bool,
+}
+
+impl Portal {
+    pub fn fold(&self) -> Fold {
+        let cell = |(x, y): (i32, i32)| Vec2::new(x as f32, y as f32);
+        Fold::turn(self.turns, cell(self.from), cell(self.to))
+    }
+}
+
+/// Portals looked up by the cell they start from
+#[derive(Debug, Clone)]
+pub struct Portals {
+    width: usize,
+    height: usize,
+    cells: Vec<Option<usize>>,
+    portals: Vec<Portal>,
+}
+
+impl Portals {
+    pub fn empty() -> Self {
+        Self::new(0, 0, Vec::new())
+    }
+
+    pub fn new(width: usize, height: usize, portals: Vec<Portal>) -> Self {
+        let mut cells = vec![None; width * height];
+        for (index, portal) in portals.iter().enumerate() {
+            let (x, y) = portal.from;
+            if x >= 0 && y >= 0 && (x as usize) < width && (y as usize) < height {
+                cells[y as usize * width + x as usize] = Some(index);
+            }
+        }
+        Self {
+            width,
+            height,
+            cells,
+            portals,
+        }
+    }
+
+    pub fn in_cell(&self, x: i32, y: i32) -> Option<&Portal> {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return None;
+        }
+        self.cells[y as usize * self.width + x as usize].map(|index| &self.portals[index])
+    }
+
+    pub fn at(&self, point: Vec2) -> Option<&Portal> {
+        self.in_cell(point.x.floor() as i32, point.y.floor() as i32)
+    }
+
+    /// Where a body standing at `position` is sent, as the fold to apply to its
+    /// position and heading
+    pub fn travel(&self, position: Vec2) -> Option<Fold> {
+        self.at(position).map(Portal::fold)
+    }
+
+    /// First sampled point of the line from `from` to `to` that is inside `portal`
+    fn entry(&self, from: Vec2, to: Vec2, portal: &Portal) -> Option<Vec2> {
+        let distance = from.distance(to);
+        let steps = (distance / SAMPLE_STEP).ceil().max(1.0) as usize;
+        (0..=steps)
+            .map(|i| from.lerp(to, i as f32 / steps as f32))
+            .find(|&point| self.at(point) == Some(portal))
+    }
+
+    /// Where `to` appears when looking from `from`, if it can be seen at all. Either the
+    /// straight line is clear and stays out of windows, or it is clear up to a window
+    /// and clear from the window's destination on to `to`; then `to` appears where
+    /// the window's fold maps it from.
+    pub fn seen_at(&self, from: Vec2, to: Vec2, map: &[Vec<i32>]) -> Option<Vec2> {
+        let windows = || self.portals.iter().filter(|portal| portal.window);
+        let through_window = windows().any(|portal| self.entry(from, to, portal).is_some());
+        if !through_window && grid::line_of_sight(from, to, map) {
+            return Some(to);
+        }
+
+        windows().find_map(|portal| {
+            let fold = portal.fold();
+            let image = fold.unfold(to);
+            let entry = self.entry(from, image, portal)?;
+            let clear = grid::line_of_sight(from, entry, map)
+                && grid::line_of_sight(fold.point(entry), to, map);
+            clear.then_some(image)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_portal_line_of_sight() {
+        // Two rooms side by side with no door; a window at (2, 1) shows the room on the right
+        let map = vec![
+            vec![1, 1, 1, 1, 1, 1, 1, 1],
+            vec![1, 0, 0, 1, 1, 0, 0, 1],
+            vec![1, 1, 1, 1, 1, 1, 1, 1],
+        ];
+        let window = Portal {
+            from: (2, 1),
+            to: (5, 1),
+            turns: 0,
+            window: true,
+        };
+        let portals = Portals::new(8, 3, vec![window.clone()]);
+        assert_eq!(portals.at(Vec2::new(2.5, 1.5)), Some(&window));
+        assert_eq!(
+            portals
+                .travel(Vec2::new(2.2, 1.5))
+                .unwrap()
+                .point(Vec2::new(2.2, 1.5)),
+            Vec2::new(5.2, 1.5)
+        );
+
+        let (left, right) = (Vec2::new(1.5, 1.5), Vec2::new(6.5, 1.5));
+        assert!(!grid::line_of_sight(left, right, &map));
+        assert_eq!(
+            portals.seen_at(left, right, &map),
+            Some(Vec2::new(3.5, 1.5))
+        );
+
+        let teleporter = Portal {
+            window: false,
+            ..window
+        };
+        let portals = Portals::new(8, 3, vec![teleporter]);
+        assert_eq!(portals.seen_at(left, right, &map), None);
+    }
+}
@@ -1,8 +1,24 @@
+use crate::assets::Vfs;
 use crate::engine::camera::Camera;
-use crate::engine::texture::TextureCache;
+use crate::engine::fold::Fold;
+use crate::engine::heights::{HeightMap, DEFAULT_WALL_HEIGHT};
+use crate::engine::lighting::{LightMap, Lighting, PointLight};
+use crate::engine::mirrors::Mirror;
+use crate::engine::portals::{Portal, Portals};
+use crate::engine::sectors::SectorMap;
+use crate::engine::sky::Sky;
+use crate::engine::texture::{Animation, Filter, TextureCache};
+use crate::engine::thin_walls::ThinWalls;
 use crate::game::ai::Enemy;
 use crate::game::Particle;
 use glam::Vec2;
+use std::collections::HashMap;
+
+const DEFAULT_DRAW_DISTANCE: f32 = 20.0;
+const CEILING_COLOR: [u8; 3] = [0x40, 0x40, 0x40];
+const FLOOR_COLOR: [u8; 3] = [0x80, 0x80, 0x80];
+const MAX_PORTALS: usize = 64; // Sector renderer gives up on a column after this many portals
+const DEFAULT_MAX_REFLECTIONS: u32 = 2;
 
 pub struct Raycaster {
     width: u32,
@@ -10,6 +26,73 @@ pub struct Raycaster {
     z_buffer: Vec<f32>,
     map: Vec<Vec<i32>>,
     texture_cache: TextureCache,
+    draw_distance: f32,
+    lighting: Lighting,
+    light_map: LightMap,
+    heights: HeightMap,
+    thin_walls: ThinWalls,
+    sectors: Option<SectorMap>, // Render with the sector renderer instead of the grid
+    glass_depth: Vec<f32>, // Nearest see-through wall per column, for ordering sprites around it
+    sky: Option<Sky>,      // Drawn above outdoor cells; without one they show the fog color
+    mirrors: HashMap<i32, Mirror>, // Reflective wall IDs
+    max_reflections: u32,  // Mirrors past this many bounces draw as plain walls
+    portals: Portals,
+    folded: Vec<Vec<FoldedView>>, // Per column, the mirrors and window portals the ray went through
+}
+
+/// The part of a column seen in a mirror or through a window portal
+struct FoldedView {
+    fold: Fold,
+    rows: (i32, i32),
+    near: f32, // Distance to the mirror or portal
+    far: f32,  // Distance to the first opaque surface seen past it
+    mirror: Option<Mirror>,
+}
+
+/// Which billboards a sprite pass draws
+#[derive(Clone, Copy, PartialEq)]
+enum Pass {
+    BehindGlass,
+    InFront,
+    Folded(Fold),
+}
+
+/// Where world heights land on screen this frame
+#[derive(Clone, Copy)]
+struct Projection {
+    horizon: f32,
+    eye: f32,
+    screen_height: f32,
+}
+
+impl Projection {
+    /// Looking up or down shears the view by moving the horizon
+    fn new(camera: &Camera, screen_height: f32) -> Self {
+        Self {
+            horizon: screen_height / 2.0 + camera.pitch * screen_height,
+            eye: camera.z,
+            screen_height,
+        }
+    }
+
+    fn project(&self, z: f32, distance: f32) -> f32 {
+        self.horizon - (z - self.eye) * self.screen_height / distance
+    }
+
+    /// World height seen at row `y` of a surface `distance` away
+    fn height_at(&self, y: i32, distance: f32) -> f32 {
+        self.eye - (y as f32 + 0.5 - self.horizon) * distance / self.screen_height
+    }
+}
+
+/// One textured column of a grid wall face or a thin wall
+struct WallSlice {
+    texture: usize,
+    u: f32, // Horizontal texture coordinate, 0..1
+    base: f32,
+    top: f32,
+    distance: f32,
+    light: f32,
 }
 
 impl Raycaster {
@@ -30,6 +113,18 @@ impl Raycaster {
             z_buffer: vec![0.0; width as usize],
             map,
             texture_cache: TextureCache::new(),
+            draw_distance: DEFAULT_DRAW_DISTANCE,
+            lighting: Lighting::default(),
+            light_map: LightMap::empty(),
+            heights: HeightMap::flat(6, 6),
+            thin_walls: ThinWalls::empty(),
+            sectors: None,
+            glass_depth: vec![f32::INFINITY; width as usize],
+            sky: None,
+            mirrors: HashMap::new(),
+            max_reflections: DEFAULT_MAX_REFLECTIONS,
+            portals: Portals::empty(),
+            folded: (0..width).map(|_| Vec::new()).collect(),
         }
     }
 
@@ -37,8 +132,64 @@ impl Raycaster {
         self.map = map;
     }
 
-    pub fn load_texture<P: AsRef<std::path::Path>>(&mut self, path: P) -> Result<usize, String> {
-        self.texture_cache.load_texture(path)
+    pub fn set_lighting(&mut self, lighting: Lighting) {
+        self.lighting = lighting;
+    }
+
+    pub fn set_heights(&mut self, heights: HeightMap) {
+        self.heights = heights;
+    }
+
+    pub fn set_thin_walls(&mut self, thin_walls: ThinWalls) {
+        self.thin_walls = thin_walls;
+    }
+
+    pub fn set_sectors(&mut self, sectors: Option<SectorMap>) {
+        self.sectors = sectors;
+    }
+
+    pub fn set_sky(&mut self, sky: Option<Sky>) {
+        self.sky = sky;
+    }
+
+    pub fn set_mirrors(&mut self, mirrors: HashMap<i32, Mirror>) {
+        self.mirrors = mirrors;
+    }
+
+    pub fn set_max_reflections(&mut self, bounces: u32) {
+        self.max_reflections = bounces;
+    }
+
+    pub fn set_portals(&mut self, portals: Portals) {
+        self.portals = portals;
+    }
+
+    pub fn set_light_map(&mut self, light_map: LightMap) {
+        self.light_map = light_map;
+    }
+
+    pub fn set_draw_distance(&mut self, distance: f32) {
+        self.draw_distance = distance.max(1.0);
+    }
+
+    /// Texture detail: `base_mip` levels are skipped at every distance
+    pub fn set_texture_quality(&mut self, base_mip: usize, filter: Filter) {
+        self.texture_cache.set_base_mip(base_mip);
+        self.texture_cache.set_filter(filter);
+    }
+
+    pub fn load_texture<S: AsRef<str>>(
+        &mut self,
+        vfs: &Vfs,
+        frames: &[S],
+        animation: Animation,
+    ) -> Result<usize, String> {
+        self.texture_cache.load_texture(vfs, frames, animation)
+    }
+
+    /// Game time in seconds; animated wall textures show the frame and scroll for it
+    pub fn set_time(&mut self, time: f32) {
+        self.texture_cache.set_time(time);
     }
 
     pub fn render(
@@ -46,29 +197,126 @@ impl Raycaster {
         camera: &Camera,
         enemies: &[Enemy],
         particles: &[Particle],
+        lights: &[PointLight],
         frame: &mut [u8],
     ) {
-        // Clear frame with ceiling and floor colors
+        // Clear frame with ceiling and floor colors, fogged by each row's distance
+        // from the camera as if the floor and ceiling of its cell went on forever.
+        let view = Projection::new(camera, self.height as f32);
+        let horizon = view.horizon;
+        let above_floor = (camera.z - self.heights.floor_at(camera.position)).max(0.05);
+        let below_ceiling = (self.heights.ceiling_at(camera.position) - camera.z).max(0.05);
         for y in 0..self.height {
+            let offset = (y as f32 + 0.5) - horizon;
+            let eye_to_plane = if offset < 0.0 {
+                below_ceiling
+            } else {
+                above_floor
+            };
+            let row_distance = eye_to_plane * self.height as f32 / offset.abs();
+            let base = if offset < 0.0 {
+                CEILING_COLOR
+            } else {
+                FLOOR_COLOR
+            };
+            let [r, g, b] = self
+                .lighting
+                .shade(base, row_distance, self.lighting.ambient);
+
             for x in 0..self.width {
                 let idx = ((y * self.width + x) * 4) as usize;
-                if y < self.height / 2 {
-                    // Ceiling (dark gray)
-                    frame[idx] = 0x40; // R
-                    frame[idx + 1] = 0x40; // G
-                    frame[idx + 2] = 0x40; // B
-                    frame[idx + 3] = 0xff; // A
-                } else {
-                    // Floor (light gray)
-                    frame[idx] = 0x80; // R
-                    frame[idx + 1] = 0x80; // G
-                    frame[idx + 2] = 0x80; // B
-                    frame[idx + 3] = 0xff; // A
+                frame[idx] = r; // R
+                frame[idx + 1] = g; // G
+                frame[idx + 2] = b; // B
+                frame[idx + 3] = 0xff; // A
+            }
+        }
+
+        // Walls come from the sector map when there is one, otherwise from the grid
+        let layers = if self.sectors.is_some() {
+            self.cast_sectors(camera, lights, &view, frame)
+        } else {
+            self.cast_grid(camera, lights, &view, frame)
+        };
+
+        // Sprites seen in mirrors and through windows, then the mirrors' tint over what they show
+        let mut folds: Vec<Fold> = Vec::new();
+        for view in self.folded.iter().flatten() {
+            if !folds.contains(&view.fold) {
+                folds.push(view.fold);
+            }
+        }
+        for fold in folds {
+            let pass = Pass::Folded(fold);
+            self.draw_billboards(camera, enemies, particles, lights, frame, pass);
+        }
+        self.tint_reflections(frame);
+
+        // Sprites behind glass, then see-through walls back to front, then everything in front
+        self.draw_billboards(camera, enemies, particles, lights, frame, Pass::BehindGlass);
+        for (x, clip, slice) in layers.iter().rev() {
+            self.draw_slice(frame, &view, *x, slice, *clip);
+        }
+        self.draw_billboards(camera, enemies, particles, lights, frame, Pass::InFront);
+    }
+
+    fn tint_reflections(&self, frame: &mut [u8]) {
+        for (x, views) in self.folded.iter().enumerate() {
+            for view in views {
+                let Some(mirror) = view.mirror else {
+                    continue;
+                };
+                for y in view.rows.0.max(0)..view.rows.1.min(self.height as i32) {
+                    let idx = (y as usize * self.width as usize + x) * 4;
+                    let rgb = [frame[idx], frame[idx + 1], frame[idx + 2]];
+                    put_pixel(frame, self.width, x as i32, y, mirror.apply(rgb));
                 }
             }
         }
+    }
+
+    /// The first opaque surface past the latest mirror or window bounds what shows in it
+    fn end_folded_view(&mut self, x: u32, distance: f32) {
+        if let Some(view) = self.folded[x as usize].last_mut() {
+            view.far = view.far.min(distance);
+        }
+    }
+
+    /// Static light from the light map plus this frame's dynamic lights
+    fn light_at(&self, lights: &[PointLight], cell_light: f32, point: Vec2) -> f32 {
+        let dynamic: f32 = lights.iter().map(|light| light.contribution(point)).sum();
+        self.lighting.light_level(cell_light + dynamic)
+    }
+
+    /// How brightly something standing at `point` is lit, as its sprite would be drawn
+    pub fn light_level(&self, lights: &[PointLight], point: Vec2) -> f32 {
+        self.light_at(lights, self.light_map.sample(point), point)
+    }
+
+    /// Floor height under a point, from the sector map when rendering sectors
+    fn floor_at(&self, point: Vec2) -> f32 {
+        self.sectors
+            .as_ref()
+            .and_then(|sectors| sectors.floor_at(point))
+            .unwrap_or_else(|| self.heights.floor_at(point))
+    }
+
+    fn cast_grid(
+        &mut self,
+        camera: &Camera,
+        lights: &[PointLight],
+        view: &Projection,
+        frame: &mut [u8],
+    ) -> Vec<(u32, (i32, i32), WallSlice)> {
+        // Cast rays for walls. The DDA keeps going past short walls and floor steps,
+        // drawing front to back into whatever rows of the column are still open.
+        // See-through walls are collected on the way and composited back to front afterwards.
+        // Mirrors and window portals don't turn the ray; it carries on straight through a
+        // folded copy of the map.
+        let mut layers: Vec<(u32, (i32, i32), WallSlice)> = Vec::new();
+        let map_width = self.map[0].len() as f32;
+        let map_height = self.map.len() as f32;
 
-        // Cast rays for walls
         for x in 0..self.width {
             let camera_x = 2.0 * x as f32 / self.width as f32 - 1.0;
             let ray_dir = Vec2::new(
@@ -97,104 +345,554 @@ impl Raycaster {
                 },
             );
 
-            // DDA Algorithm
-            let mut hit = false;
-            let mut side = 0; // 0 for x-side, 1 for y-side
+            // Rows [clip_top, clip_bottom) of this column are still open
+            let mut clip = (0, self.height as i32);
+            let mut prev_floor = self.heights.floor_at(camera.position);
+            let mut prev_ceiling = self.heights.ceiling_at(camera.position);
+            let mut last_open = map_pos; // Open cell in front of the face being drawn
+            let mut nearest_wall = None;
+            let mut closed = false;
+            let column_layers = layers.len();
+            let mut entered = 0.0; // Distance at which the ray entered the current cell
+            let mut fold = Fold::IDENTITY;
+            self.folded[x as usize].clear();
+
+            // DDA Algorithm, bounded so open maps or a camera outside the grid can't hang the frame
+            loop {
+                // Give up once the next cell is past the draw distance or the ray
+                // is outside the grid and heading further away from it
+                let (cell, heading) = (fold.cell(map_pos), fold.direction(step));
+                let left_grid = (cell.x < 0.0 && heading.x < 0.0)
+                    || (cell.x >= map_width && heading.x > 0.0)
+                    || (cell.y < 0.0 && heading.y < 0.0)
+                    || (cell.y >= map_height && heading.y > 0.0);
+                let distance = side_dist.x.min(side_dist.y);
+
+                // Thin walls standing in the open cell the ray is crossing, nearest first
+                if last_open == map_pos {
+                    let (cell_x, cell_y) = (cell.x as i32, cell.y as i32);
+                    let span = entered..=distance.min(self.draw_distance);
+                    let origin = fold.point(camera.position);
+                    let mut hits: Vec<_> = self
+                        .thin_walls
+                        .in_cell(cell_x, cell_y)
+                        .iter()
+                        .filter_map(|wall| {
+                            let (distance, u) = wall.intersect(origin, fold.direction(ray_dir))?;
+                            span.contains(&distance).then_some((wall, distance, u))
+                        })
+                        .collect();
+                    hits.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+                    for (wall, distance, u) in hits {
+                        let hit_point = fold.point(camera.position + ray_dir * distance);
+                        let mut light =
+                            self.light_at(lights, self.light_map.get(cell_x, cell_y), hit_point);
+                        if wall.faces_y() {
+                            light *= self.lighting.side_shade;
+                        }
+                        let slice = WallSlice {
+                            texture: (wall.texture - 1) as usize,
+                            u,
+                            base: prev_floor,
+                            top: prev_floor + self.heights.wall(cell_x, cell_y),
+                            distance,
+                            light,
+                        };
+
+                        if self.is_translucent(slice.texture) {
+                            layers.push((x, clip, slice));
+                            continue;
+                        }
+                        nearest_wall.get_or_insert(distance);
+                        self.end_folded_view(x, distance);
+                        self.draw_slice(frame, view, x, &slice, clip);
+                        clip.1 = clip.1.min(view.project(slice.top, distance).ceil() as i32);
+                        closed = slice.top >= prev_ceiling;
+                        break;
+                    }
+
+                    // The ceiling of this cell hides everything above it out to its far side
+                    let ceiling_bottom =
+                        view.project(prev_ceiling, distance.min(self.draw_distance));
+                    clip.0 = clip.0.max(ceiling_bottom as i32);
+                    if closed || clip.0 >= clip.1 {
+                        closed = true;
+                        break;
+                    }
+                }
+                entered = distance;
+
+                if left_grid || distance > self.draw_distance {
+                    break;
+                }
 
-            while !hit {
                 // Jump to next square
-                if side_dist.x < side_dist.y {
+                let side = if side_dist.x < side_dist.y {
                     side_dist.x += delta_dist.x;
                     map_pos.x += step.x;
-                    side = 0;
+                    0 // x-side
                 } else {
                     side_dist.y += delta_dist.y;
                     map_pos.y += step.y;
-                    side = 1;
+                    1 // y-side
+                };
+
+                // Distance to the face we just crossed, and to the far side of this cell
+                let near = distance.max(0.001);
+                let far = side_dist.x.min(side_dist.y);
+
+                // Window portals carry the ray on from their destination
+                let mut cell = fold.cell(map_pos);
+                while self.folded[x as usize].len() < MAX_PORTALS {
+                    let portal = self.portals.in_cell(cell.x as i32, cell.y as i32);
+                    let Some(next) = portal.filter(|portal| portal.window).map(Portal::fold) else {
+                        break;
+                    };
+                    nearest_wall.get_or_insert(near);
+                    self.end_folded_view(x, near);
+                    fold = fold.then(next);
+                    self.folded[x as usize].push(FoldedView {
+                        fold,
+                        rows: clip,
+                        near,
+                        far: f32::INFINITY,
+                        mirror: None,
+                    });
+                    cell = fold.cell(map_pos);
+                }
+
+                let inside =
+                    cell.x >= 0.0 && cell.x < map_width && cell.y >= 0.0 && cell.y < map_height;
+                if !inside {
+                    continue;
+                }
+                let (cell_x, cell_y) = (cell.x as i32, cell.y as i32);
+
+                // The face we see is lit from the open cell in front of it
+                let hit_point = fold.point(camera.position + ray_dir * near);
+                let lit_from = fold.cell(last_open);
+                let cell_light = self.light_map.get(lit_from.x as i32, lit_from.y as i32);
+                let mut light = self.light_at(lights, cell_light, hit_point);
+                let face_axis = fold.axis(side); // Which way the face runs on the real map
+                if face_axis == 1 {
+                    light *= self.lighting.side_shade;
                 }
 
-                // Check if ray has hit a wall
-                if map_pos.x >= 0.0
-                    && map_pos.x < self.map[0].len() as f32
-                    && map_pos.y >= 0.0
-                    && map_pos.y < self.map.len() as f32
-                {
-                    if self.map[map_pos.y as usize][map_pos.x as usize] > 0 {
-                        hit = true;
+                let tile = self.map[cell_y as usize][cell_x as usize];
+                if tile > 0 {
+                    let base = self.heights.floor(cell_x, cell_y);
+                    let top = base + self.heights.wall(cell_x, cell_y);
+
+                    // Mirrors fold the ray back into the map, up to the bounce limit
+                    let mirror = self.mirrors.get(&tile).copied();
+                    let views = &self.folded[x as usize];
+                    let bounces = views.iter().filter(|view| view.mirror.is_some()).count() as u32;
+                    if let Some(mirror) = mirror.filter(|_| bounces < self.max_reflections) {
+                        nearest_wall.get_or_insert(near);
+                        self.end_folded_view(x, near);
+                        let rows = (
+                            view.project(top, near) as i32,
+                            view.project(base, near) as i32,
+                        );
+                        clip = (clip.0.max(rows.0), clip.1.min(rows.1));
+
+                        // The face is where the virtual space flips
+                        let face = if side == 0 {
+                            map_pos.x + if step.x > 0.0 { 0.0 } else { 1.0 }
+                        } else {
+                            map_pos.y + if step.y > 0.0 { 0.0 } else { 1.0 }
+                        };
+                        fold = fold.reflect(side, face);
+                        self.folded[x as usize].push(FoldedView {
+                            fold,
+                            rows: clip,
+                            near,
+                            far: f32::INFINITY,
+                            mirror: Some(mirror),
+                        });
+
+                        // The wall cell now stands for the open cell in front of the mirror
+                        last_open = map_pos;
+                        if clip.0 >= clip.1 {
+                            closed = true;
+                            break;
+                        }
+                        continue;
                     }
+
+                    // Compute texture coordinates
+                    let wall_x = if face_axis == 0 {
+                        hit_point.y
+                    } else {
+                        hit_point.x
+                    };
+                    let slice = WallSlice {
+                        texture: (tile - 1) as usize,
+                        u: wall_x - wall_x.floor(),
+                        base,
+                        top,
+                        distance: near,
+                        light,
+                    };
+
+                    // The ray carries on behind see-through walls
+                    if self.is_translucent(slice.texture) {
+                        layers.push((x, clip, slice));
+                        continue;
+                    }
+                    nearest_wall.get_or_insert(near);
+                    self.end_folded_view(x, near);
+                    self.draw_slice(frame, view, x, &slice, clip);
+
+                    // Looking down on a short wall shows its top out to the far edge of the cell
+                    let mut covered_to = view.project(top, near);
+                    if top < view.eye {
+                        let cap_top = view.project(top, far);
+                        let rgb = self.lighting.shade(FLOOR_COLOR, near, light);
+                        fill_span(
+                            frame,
+                            self.width,
+                            x as i32,
+                            (cap_top, covered_to),
+                            clip,
+                            rgb,
+                        );
+                        covered_to = cap_top;
+                    }
+                    clip.1 = clip.1.min(covered_to.ceil() as i32);
+
+                    // A wall reaching the ceiling hides everything behind it
+                    closed = top >= prev_ceiling;
+                    prev_floor = top;
+                } else {
+                    let floor = self.heights.floor(cell_x, cell_y);
+                    let ceiling = self.heights.ceiling(cell_x, cell_y);
+                    let face_light = light * self.lighting.side_shade;
+
+                    // Step up: draw the riser between the two floor heights
+                    if floor > prev_floor {
+                        let riser_top = view.project(floor, near);
+                        let riser = (riser_top, view.project(prev_floor, near));
+                        let rgb = self.lighting.shade(FLOOR_COLOR, near, face_light);
+                        fill_span(frame, self.width, x as i32, riser, clip, rgb);
+                        clip.1 = clip.1.min(riser_top.ceil() as i32);
+                    }
+
+                    // Lower ceiling: draw the face hanging down from the previous one.
+                    // Coming in from outdoors, the sky shows above the roof line instead.
+                    if ceiling < prev_ceiling {
+                        let face_bottom = view.project(ceiling, near);
+                        if prev_ceiling.is_infinite() {
+                            let rows = (clip.0, (face_bottom as i32).min(clip.1));
+                            self.draw_sky(frame, view, x, fold.direction(ray_dir), rows);
+                        } else {
+                            let face = (view.project(prev_ceiling, near), face_bottom);
+                            let rgb = self.lighting.shade(CEILING_COLOR, near, face_light);
+                            fill_span(frame, self.width, x as i32, face, clip, rgb);
+                        }
+                        clip.0 = clip.0.max(face_bottom as i32);
+                    }
+
+                    closed = floor >= prev_ceiling || ceiling <= prev_floor;
+                    prev_floor = floor;
+                    prev_ceiling = ceiling;
+                    last_open = map_pos;
+                }
+
+                if closed || clip.0 >= clip.1 {
+                    closed = true;
+                    break;
                 }
             }
 
-            // Compute distance to wall
-            let perp_wall_dist = if side == 0 {
-                side_dist.x - delta_dist.x
-            } else {
-                side_dist.y - delta_dist.y
-            };
+            self.z_buffer[x as usize] = nearest_wall.unwrap_or(self.draw_distance);
+            self.glass_depth[x as usize] = layers[column_layers..]
+                .first()
+                .map_or(f32::INFINITY, |(_, _, slice)| slice.distance);
+            if !closed {
+                // The ray ran out of distance or left the grid; fade what's left into fog,
+                // under open sky if it ended outdoors
+                let band = (
+                    view.project(prev_floor + DEFAULT_WALL_HEIGHT, self.draw_distance),
+                    view.project(prev_floor, self.draw_distance),
+                );
+                if prev_ceiling.is_infinite() {
+                    let rows = (clip.0, (band.0.ceil() as i32).min(clip.1));
+                    self.draw_sky(frame, view, x, fold.direction(ray_dir), rows);
+                }
+                let fog_color = self.lighting.fog_color;
+                fill_span(frame, self.width, x as i32, band, clip, fog_color);
+            }
+        }
 
-            self.z_buffer[x as usize] = perp_wall_dist;
+        layers
+    }
 
-            // Compute wall height
-            let line_height = (self.height as f32 / perp_wall_dist) as i32;
+    /// Each column's ray walks from sector to sector through portals, drawing floors,
+    /// ceilings and walls front to back into the rows still open, like the grid pass
+    fn cast_sectors(
+        &mut self,
+        camera: &Camera,
+        lights: &[PointLight],
+        view: &Projection,
+        frame: &mut [u8],
+    ) -> Vec<(u32, (i32, i32), WallSlice)> {
+        let Some(sectors) = &self.sectors else {
+            return Vec::new();
+        };
+        let start = sectors.sector_at(camera.position);
+
+        for x in 0..self.width {
+            let camera_x = 2.0 * x as f32 / self.width as f32 - 1.0;
+            let ray_dir = Vec2::new(
+                camera.direction.x + camera.plane.x * camera_x,
+                camera.direction.y + camera.plane.y * camera_x,
+            );
+
+            let mut clip = (0, self.height as i32);
+            let mut current = start;
+            self.folded[x as usize].clear();
+            let mut entered = 0.0;
+            let mut last_floor = self.floor_at(camera.position);
+            let mut nearest_wall = None;
+            let mut closed = false;
+
+            for _ in 0..MAX_PORTALS {
+                let Some(index) = current else {
+                    break;
+                };
+                let sector = &sectors.sectors[index];
+                let Some((wall, distance, u)) = sector.exit(camera.position, ray_dir, entered)
+                else {
+                    break;
+                };
+                let reach = distance.min(self.draw_distance);
+                let hit_point = camera.position + ray_dir * reach;
+                let floor = sector.floor.at(hit_point);
+                let ceiling = sector.ceiling.at(hit_point);
+
+                // Floor and ceiling of this sector out to where the ray leaves it
+                let middle = camera.position + ray_dir * (entered + reach) / 2.0;
+                let plane_light = self.light_at(lights, self.light_map.sample(middle), middle);
+                let floor_top = view.project(floor, reach);
+                let rows = (floor_top.max(clip.0 as f32).max(0.0) as i32, clip.1);
+                let plane = (sector.floor.at(middle), FLOOR_COLOR);
+                self.draw_plane(frame, view, x, rows, plane, plane_light);
+                clip.1 = clip.1.min(floor_top.ceil() as i32);
 
-            // Compute drawing bounds
-            let mut draw_start = -line_height / 2 + self.height as i32 / 2;
-            if draw_start < 0 {
-                draw_start = 0;
+                let ceiling_bottom = view.project(ceiling, reach);
+                let rows = (clip.0, ceiling_bottom.min(clip.1 as f32) as i32);
+                if sector.outdoor {
+                    self.draw_sky(frame, view, x, ray_dir, rows);
+                } else {
+                    let plane = (sector.ceiling.at(middle), CEILING_COLOR);
+                    self.draw_plane(frame, view, x, rows, plane, plane_light);
+                }
+                clip.0 = clip.0.max(ceiling_bottom as i32);
+
+                last_floor = floor;
+                if distance > self.draw_distance {
+                    break;
+                }
+
+                // The wall is lit from just inside this sector
+                let inside = hit_point - ray_dir * 0.01;
+                let mut light = self.light_at(lights, self.light_map.sample(inside), hit_point);
+                if wall.faces_y() {
+                    light *= self.lighting.side_shade;
+                }
+                let slice = |base: f32, top: f32| WallSlice {
+                    texture: (wall.texture - 1) as usize,
+                    u,
+                    base,
+                    top,
+                    distance,
+                    light,
+                };
+
+                let Some(next) = wall.portal else {
+                    nearest_wall = Some(distance);
+                    self.draw_slice(frame, view, x, &slice(floor, ceiling), clip);
+                    closed = true;
+                    break;
+                };
+
+                // Through a portal: steps where the next sector's ceiling is lower or its floor higher
+                let next_sector = &sectors.sectors[next];
+                let next_floor = next_sector.floor.at(hit_point);
+                let next_ceiling = next_sector.ceiling.at(hit_point);
+                if next_ceiling < ceiling {
+                    self.draw_slice(frame, view, x, &slice(next_ceiling, ceiling), clip);
+                    clip.0 = clip.0.max(view.project(next_ceiling, distance) as i32);
+                }
+                if next_floor > floor {
+                    self.draw_slice(frame, view, x, &slice(floor, next_floor), clip);
+                    clip.1 = clip.1.min(view.project(next_floor, distance).ceil() as i32);
+                }
+
+                current = Some(next);
+                entered = distance;
+                if clip.0 >= clip.1 {
+                    closed = true;
+                    break;
+                }
             }
-            let mut draw_end = line_height / 2 + self.height as i32 / 2;
-            if draw_end >= self.height as i32 {
-                draw_end = self.height as i32 - 1;
+
+            self.z_buffer[x as usize] = nearest_wall.unwrap_or(self.draw_distance);
+            self.glass_depth[x as usize] = f32::INFINITY;
+            if !closed {
+                // Past the draw distance; fade what's left into fog
+                let band = (
+                    view.project(last_floor + DEFAULT_WALL_HEIGHT, self.draw_distance),
+                    view.project(last_floor, self.draw_distance),
+                );
+                let fog_color = self.lighting.fog_color;
+                fill_span(frame, self.width, x as i32, band, clip, fog_color);
             }
+        }
 
-            // Compute texture coordinates
-            let wall_x = if side == 0 {
-                camera.position.y + perp_wall_dist * ray_dir.y
-            } else {
-                camera.position.x + perp_wall_dist * ray_dir.x
+        Vec::new()
+    }
+
+    /// Fill rows of a floor or ceiling at `height`, fogged by each row's distance
+    fn draw_plane(
+        &self,
+        frame: &mut [u8],
+        view: &Projection,
+        x: u32,
+        (start, end): (i32, i32),
+        (height, color): (f32, [u8; 3]),
+        light: f32,
+    ) {
+        let eye_to_plane = (view.eye - height).abs().max(0.05);
+        for y in start.max(0)..end.min(self.height as i32) {
+            let offset = (y as f32 + 0.5 - view.horizon).abs().max(0.5);
+            let distance = eye_to_plane * view.screen_height / offset;
+            let rgb = self.lighting.shade(color, distance, light);
+            put_pixel(frame, self.width, x as i32, y, rgb);
+        }
+    }
+
+    /// Fill rows of column `x` with the sky seen along `ray_dir`
+    fn draw_sky(
+        &self,
+        frame: &mut [u8],
+        view: &Projection,
+        x: u32,
+        ray_dir: Vec2,
+        (start, end): (i32, i32),
+    ) {
+        let angle = ray_dir.y.atan2(ray_dir.x);
+        for y in start.max(0)..end.min(self.height as i32) {
+            // The panorama spans the upper half of the screen when looking straight ahead
+            let v = 1.0 + (y as f32 + 0.5 - view.horizon) / (view.screen_height / 2.0);
+            let rgb = match &self.sky {
+                Some(sky) => sky.sample(angle, v),
+                None => self.lighting.fog_color,
             };
-            let wall_x = wall_x - wall_x.floor();
-
-            // Get the texture for this wall
-            let tex_num = (self.map[map_pos.y as usize][map_pos.x as usize] - 1) as usize;
-            if let Some(texture) = self.texture_cache.get_texture(tex_num) {
-                // Draw the textured wall
-                for y in draw_start..draw_end {
-                    let d = y * 256 - self.height as i32 * 128 + line_height * 128;
-                    let tex_y = ((d * texture.height as i32) / line_height) / 256;
-
-                    let color =
-                        texture.get_pixel((wall_x * texture.width as f32) as u32, tex_y as u32);
-
-                    // Convert color from u32 RGBA to bytes
-                    let r = ((color >> 24) & 0xFF) as u8;
-                    let g = ((color >> 16) & 0xFF) as u8;
-                    let b = ((color >> 8) & 0xFF) as u8;
-                    let a = (color & 0xFF) as u8;
-
-                    // Apply shading based on distance and side
-                    let shade = if side == 1 { 0.7 } else { 1.0 };
-                    let distance_shade = (1.0 / (1.0 + perp_wall_dist * 0.1)).min(1.0);
-                    let final_shade = shade * distance_shade;
-
-                    let idx = ((y * self.width as i32 + x as i32) * 4) as usize;
-                    frame[idx] = (r as f32 * final_shade) as u8; // R
-                    frame[idx + 1] = (g as f32 * final_shade) as u8; // G
-                    frame[idx + 2] = (b as f32 * final_shade) as u8; // B
-                    frame[idx + 3] = a; // A
-                }
+            put_pixel(frame, self.width, x as i32, y, rgb);
+        }
+    }
+
+    fn is_translucent(&self, texture: usize) -> bool {
+        self.texture_cache
+            .get_texture(texture)
+            .is_some_and(|texture| texture.translucent)
+    }
+
+    /// Draw a wall slice into column `x`, limited to the open rows in `clip`.
+    /// Translucent textures blend over whatever is already there.
+    fn draw_slice(
+        &self,
+        frame: &mut [u8],
+        view: &Projection,
+        x: u32,
+        slice: &WallSlice,
+        clip: (i32, i32),
+    ) {
+        let Some(texture) = self.texture_cache.get_texture(slice.texture) else {
+            return;
+        };
+        let draw_start = view
+            .project(slice.top, slice.distance)
+            .max(clip.0 as f32)
+            .max(0.0) as i32;
+        let draw_end = view.project(slice.base, slice.distance).min(clip.1 as f32) as i32;
+
+        // Texels per screen pixel picks the mip level; farther walls use smaller ones
+        let footprint = texture.height as f32 * slice.distance / view.screen_height;
+        for y in draw_start..draw_end {
+            // World height at this row; the texture repeats every unit
+            let z = view.height_at(y, slice.distance);
+            let v = (slice.top - z).rem_euclid(1.0);
+            let Some(color) = self
+                .texture_cache
+                .sample(slice.texture, slice.u, v, footprint)
+            else {
+                return;
+            };
+            let alpha = (color & 0xFF) as u8;
+            if texture.translucent && alpha == 0 {
+                continue;
+            }
+
+            // Convert color from u32 RGBA to bytes
+            let r = ((color >> 24) & 0xFF) as u8;
+            let g = ((color >> 16) & 0xFF) as u8;
+            let b = ((color >> 8) & 0xFF) as u8;
+
+            // Apply side shading, light and fog
+            let rgb = self.lighting.shade([r, g, b], slice.distance, slice.light);
+            if texture.translucent {
+                blend_pixel(frame, self.width, x as i32, y, rgb, alpha);
+            } else {
+                put_pixel(frame, self.width, x as i32, y, rgb);
             }
         }
+    }
+
+    /// Rows of a column a billboard `distance` away may cover in this pass, if any.
+    /// Billboards are drawn around the see-through walls of each column, and once
+    /// more for each mirror, inside what the mirror shows.
+    fn visible_rows(&self, column: i32, distance: f32, pass: Pass) -> Option<(i32, i32)> {
+        let column = column as usize;
+        let behind_glass = match pass {
+            Pass::Folded(fold) => {
+                return self.folded[column]
+                    .iter()
+                    .find(|r| r.fold == fold && distance > r.near && distance < r.far)
+                    .map(|r| r.rows);
+            }
+            Pass::BehindGlass => true,
+            Pass::InFront => false,
+        };
+        let in_pass = (distance >= self.glass_depth[column]) == behind_glass;
+        (distance < self.z_buffer[column] && in_pass).then_some((0, self.height as i32))
+    }
+
+    /// Draw the enemies and particles belonging to one pass
+    fn draw_billboards(
+        &self,
+        camera: &Camera,
+        enemies: &[Enemy],
+        particles: &[Particle],
+        lights: &[PointLight],
+        frame: &mut [u8],
+        pass: Pass,
+    ) {
+        let view = Projection::new(camera, self.height as f32);
+        // Where things appear; in a mirror that's their reflected position
+        let seen_at = |position: Vec2| match pass {
+            Pass::Folded(fold) => fold.unfold(position),
+            _ => position,
+        };
 
         // Sort sprites by distance
         let mut sprite_distances: Vec<(usize, f32)> = enemies
             .iter()
             .enumerate()
-            .map(|(i, enemy)| {
-                let dx = enemy.position.x - camera.position.x;
-                let dy = enemy.position.y - camera.position.y;
-                (i, dx * dx + dy * dy)
-            })
+            .map(|(i, enemy)| (i, seen_at(enemy.position).distance_squared(camera.position)))
             .collect();
         sprite_distances.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
 
@@ -203,7 +901,7 @@ impl Raycaster {
             let enemy = &enemies[i];
 
             // Translate sprite position relative to camera
-            let sprite_pos = enemy.position - camera.position;
+            let sprite_pos = seen_at(enemy.position) - camera.position;
 
             // Transform sprite with the inverse camera matrix
             let inv_det =
@@ -219,11 +917,17 @@ impl Raycaster {
             let sprite_height = (self.height as f32 / transform_y).abs() as i32;
             let sprite_width = sprite_height; // Assuming square sprites, might change later
 
-            // Compute drawing bounds
-            let draw_start_y = -sprite_height / 2 + self.height as i32 / 2;
-            let draw_end_y = sprite_height / 2 + self.height as i32 / 2;
+            // Compute drawing bounds; sprites stand on the floor of their cell
+            let floor = self.floor_at(enemy.position);
+            let draw_end_y = view.project(floor, transform_y) as i32;
+            let draw_start_y = draw_end_y - sprite_height;
             let draw_start_x = -sprite_width / 2 + sprite_screen_x;
             let draw_end_x = sprite_width / 2 + sprite_screen_x;
+            let sprite_light = self.light_at(
+                lights,
+                self.light_map.sample(enemy.position),
+                enemy.position,
+            );
 
             // Draw the sprite
             for stripe in draw_start_x..draw_end_x {
@@ -231,19 +935,22 @@ impl Raycaster {
                     continue;
                 }
 
-                if transform_y > 0.0 && transform_y < self.z_buffer[stripe as usize] {
+                if transform_y <= 0.0 {
+                    continue;
+                }
+                if let Some(rows) = self.visible_rows(stripe, transform_y, pass) {
                     for y in draw_start_y..draw_end_y {
-                        if y < 0 || y >= self.height as i32 {
+                        if y < rows.0 || y >= rows.1 {
                             continue;
                         }
 
                         let tex_x = ((stripe - (-sprite_width / 2 + sprite_screen_x))
-                            * enemy.texture.width as i32
+                            * enemy.texture().width as i32
                             / sprite_width) as u32;
-                        let tex_y = ((y - draw_start_y) * enemy.texture.height as i32
+                        let tex_y = ((y - draw_start_y) * enemy.texture().height as i32
                             / sprite_height) as u32;
 
-                        let color = enemy.texture.get_pixel(tex_x, tex_y);
+                        let color = enemy.texture().get_pixel(tex_x, tex_y);
                         let alpha = (color & 0xFF) as u8;
 
                         if alpha > 0 {
@@ -253,11 +960,12 @@ impl Raycaster {
                                 let g = ((color >> 16) & 0xFF) as u8;
                                 let b = ((color >> 8) & 0xFF) as u8;
 
-                                // Apply distance-based shading
-                                let distance_shade = (1.0 / (1.0 + transform_y * 0.1)).min(1.0);
-                                frame[idx] = (r as f32 * distance_shade) as u8; // R
-                                frame[idx + 1] = (g as f32 * distance_shade) as u8; // G
-                                frame[idx + 2] = (b as f32 * distance_shade) as u8; // B
+                                // Apply light and fog
+                                let [r, g, b] =
+                                    self.lighting.shade([r, g, b], transform_y, sprite_light);
+                                frame[idx] = r; // R
+                                frame[idx + 1] = g; // G
+                                frame[idx + 2] = b; // B
                                 frame[idx + 3] = alpha; // A
                             }
                         }
@@ -269,7 +977,7 @@ impl Raycaster {
         // Draw particles
         for particle in particles {
             // Translate particle position relative to camera
-            let particle_pos = particle.position - camera.position;
+            let particle_pos = seen_at(particle.position) - camera.position;
 
             // Transform particle with the inverse camera matrix
             let inv_det =
@@ -285,9 +993,10 @@ impl Raycaster {
             // Compute particle dimensions on screen
             let particle_size = (self.height as f32 / transform_y * 0.1).abs() as i32; // Smaller than sprites
 
-            // Compute drawing bounds
-            let draw_start_y = -particle_size / 2 + self.height as i32 / 2;
-            let draw_end_y = particle_size / 2 + self.height as i32 / 2;
+            // Compute drawing bounds at the height the particle flies
+            let center_y = view.project(particle.z, transform_y) as i32;
+            let draw_start_y = center_y - particle_size / 2;
+            let draw_end_y = center_y + particle_size / 2;
             let draw_start_x = -particle_size / 2 + particle_screen_x;
             let draw_end_x = particle_size / 2 + particle_screen_x;
 
@@ -298,9 +1007,9 @@ impl Raycaster {
                         continue;
                     }
 
-                    if transform_y < self.z_buffer[stripe as usize] {
+                    if let Some(rows) = self.visible_rows(stripe, transform_y, pass) {
                         for y in draw_start_y..draw_end_y {
-                            if y < 0 || y >= self.height as i32 {
+                            if y < rows.0 || y >= rows.1 {
                                 continue;
                             }
 
@@ -320,12 +1029,12 @@ impl Raycaster {
                                     let g = ((color >> 16) & 0xFF) as u8;
                                     let b = ((color >> 8) & 0xFF) as u8;
 
-                                    // Apply distance-based shading and glow effect
-                                    let distance_shade =
-                                        (1.0 / (1.0 + transform_y * 0.05)).min(1.0);
-                                    frame[idx] = (r as f32 * distance_shade) as u8; // R
-                                    frame[idx + 1] = (g as f32 * distance_shade) as u8; // G
-                                    frame[idx + 2] = (b as f32 * distance_shade) as u8; // B
+                                    // Particles glow, so only fog applies
+                                    let [r, g, b] =
+                                        self.lighting.shade_emissive([r, g, b], transform_y);
+                                    frame[idx] = r; // R
+                                    frame[idx + 1] = g; // G
+                                    frame[idx + 2] = b; // B
                                     frame[idx + 3] = alpha; // A
                                 }
                             }
@@ -336,3 +1045,35 @@ impl Raycaster {
         }
     }
 }
+
+fn put_pixel(frame: &mut [u8], width: u32, x: i32, y: i32, rgb: [u8; 3]) {
+    let idx = ((y * width as i32 + x) * 4) as usize;
+    frame[idx] = rgb[0]; // R
+    frame[idx + 1] = rgb[1]; // G
+    frame[idx + 2] = rgb[2]; // B
+    frame[idx + 3] = 0xFF; // A
+}
+
+fn blend_pixel(frame: &mut [u8], width: u32, x: i32, y: i32, rgb: [u8; 3], alpha: u8) {
+    let idx = ((y * width as i32 + x) * 4) as usize;
+    let a = alpha as f32 / 255.0;
+    for i in 0..3 {
+        frame[idx + i] = (rgb[i] as f32 * a + frame[idx + i] as f32 * (1.0 - a)) as u8;
+    }
+}
+
+/// Fill rows [top, bottom) of column `x`, limited to the open rows [clip_top, clip_bottom)
+fn fill_span(
+    frame: &mut [u8],
+    width: u32,
+    x: i32,
+    (top, bottom): (f32, f32),
+    (clip_top, clip_bottom): (i32, i32),
+    rgb: [u8; 3],
+) {
+    let start = (top.max(clip_top as f32) as i32).max(0);
+    let end = bottom.min(clip_bottom as f32) as i32;
+    for y in start..end {
+        put_pixel(frame, width, x, y, rgb);
+    }
+}
@@ -0,0 +1,186 @@
+use crate::engine::thin_walls::{ray_segment, ThinWall};
+use glam::Vec2;
+
+/// A floor or ceiling height that can slope: `height` at `anchor`, rising by `slope` per unit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Plane {
+    pub height: f32,
+    pub slope: Vec2,
+    pub anchor: Vec2,
+}
+
+impl Plane {
+    pub fn at(&self, point: Vec2) -> f32 {
+        self.height + self.slope.dot(point - self.anchor)
+    }
+}
+
+/// One edge of a sector. Edges shared with another sector are portals into it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SectorWall {
+    pub start: Vec2,
+    pub end: Vec2,
+    pub texture: i32, // Wall ID, same numbering as grid maps; portals use it for steps
+    pub portal: Option<usize>,
+}
+
+impl SectorWall {
+    /// Walls running along x face north/south and get the same side shading as grid walls
+    pub fn faces_y(&self) -> bool {
+        let edge = self.end - self.start;
+        edge.x.abs() > edge.y.abs()
+    }
+}
+
+/// A convex or concave polygon room with its own floor and ceiling
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sector {
+    pub walls: Vec<SectorWall>,
+    pub floor: Plane,
+    pub ceiling: Plane,
+    pub outdoor: bool, // The sky shows instead of the ceiling, which only caps the walls
+}
+
+impl Sector {
+    pub fn contains(&self, point: Vec2) -> bool {
+        // Even-odd rule, so the winding of the vertices doesn't matter
+        let mut inside = false;
+        for wall in &self.walls {
+            let (a, b) = (wall.start, wall.end);
+            if (a.y > point.y) != (b.y > point.y)
+                && point.x < a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x)
+            {
+                inside = !inside;
+            }
+        }
+        inside
+    }
+
+    /// The nearest wall a ray leaving this sector hits past `after`, as (wall, distance, u)
+    pub fn exit(
+        &self,
+        origin: Vec2,
+        direction: Vec2,
+        after: f32,
+    ) -> Option<(&SectorWall, f32, f32)> {
+        self.walls
+            .iter()
+            .filter_map(|wall| {
+                let (distance, u) = ray_segment(origin, direction, wall.start, wall.end)?;
+                (distance > after + 1e-4).then_some((wall, distance, u))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+    }
+}
+
+/// Non-grid level geometry for the sector renderer. Sectors are polygons given
+/// as vertex indices; edges shared by two sectors become portals between them.
+#[derive(Debug, Clone)]
+pub struct SectorMap {
+    pub sectors: Vec<Sector>,
+}
+
+impl SectorMap {
+    pub fn new(
+        vertices: &[Vec2],
+        polygons: &[Vec<usize>],
+        planes: &[(Plane, Plane)],
+        textures: &[Vec<i32>],
+    ) -> Result<Self, String> {
+        let edge = |polygon: &[usize], i: usize| (polygon[i], polygon[(i + 1) % polygon.len()]);
+        let same_edge = |a: (usize, usize), b: (usize, usize)| a == b || a == (b.1, b.0);
+
+        let mut sectors = Vec::with_capacity(polygons.len());
+        for (index, polygon) in polygons.iter().enumerate() {
+            if polygon.len() < 3 {
+                return Err(format!("Sector {} needs at least 3 vertices", index));
+            }
+            if let Some(&bad) = polygon.iter().find(|&&v| v >= vertices.len()) {
+                return Err(format!("Sector {} uses missing vertex {}", index, bad));
+            }
+
+            let walls = (0..polygon.len())
+                .map(|i| {
+                    let (a, b) = edge(polygon, i);
+                    let portal = polygons.iter().enumerate().position(|(other, others)| {
+                        other != index
+                            && (0..others.len()).any(|j| same_edge((a, b), edge(others, j)))
+                    });
+                    SectorWall {
+                        start: vertices[a],
+                        end: vertices[b],
+                        texture: textures[index][i],
+                        portal,
+                    }
+                })
+                .collect();
+
+            let (floor, ceiling) = planes[index];
+            sectors.push(Sector {
+                walls,
+                floor,
+                ceiling,
+                outdoor: false,
+            });
+        }
+
+        Ok(Self { sectors })
+    }
+
+    pub fn sector_at(&self, point: Vec2) -> Option<usize> {
+        self.sectors
+            .iter()
+            .position(|sector| sector.contains(point))
+    }
+
+    pub fn floor_at(&self, point: Vec2) -> Option<f32> {
+        self.sector_at(point)
+            .map(|index| self.sectors[index].floor.at(point))
+    }
+
+    /// Walls that aren't portals, as thin walls for collision and projectiles
+    pub fn solid_walls(&self) -> Vec<ThinWall> {
+        let walls = self.sectors.iter().flat_map(|sector| &sector.walls);
+        walls
+            .filter(|wall| wall.portal.is_none())
+            .map(|wall| ThinWall::new(wall.start, wall.end, wall.texture))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shared_edges_become_portals() {
+        let vertices = [
+            Vec2::new(0.0, 0.0),
+            Vec2::new(2.0, 0.0),
+            Vec2::new(2.0, 2.0),
+            Vec2::new(0.0, 2.0),
+            Vec2::new(4.0, 1.0),
+        ];
+        let polygons = vec![vec![0, 1, 2, 3], vec![1, 4, 2]];
+        let plane = |height, slope_x| Plane {
+            height,
+            slope: Vec2::new(slope_x, 0.0),
+            anchor: Vec2::new(2.0, 1.0),
+        };
+        let planes = [
+            (plane(0.0, 0.0), plane(1.0, 0.0)),
+            (plane(0.0, 0.25), plane(2.0, 0.0)),
+        ];
+        let textures = [vec![1; 4], vec![2; 3]];
+        let map = SectorMap::new(&vertices, &polygons, &planes, &textures).unwrap();
+
+        assert_eq!(map.sectors[0].walls[1].portal, Some(1));
+        assert_eq!(map.sectors[1].walls[2].portal, Some(0));
+        assert_eq!(map.sectors[0].walls[0].portal, None);
+        assert_eq!(map.solid_walls().len(), 5);
+
+        assert_eq!(map.sector_at(Vec2::new(1.0, 1.0)), Some(0));
+        assert_eq!(map.floor_at(Vec2::new(3.0, 1.0)), Some(0.25));
+        assert_eq!(map.sector_at(Vec2::new(5.0, 1.0)), None);
+    }
+}
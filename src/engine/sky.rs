@@ -0,0 +1,84 @@
+use crate::engine::texture::Texture;
+use std::f32::consts::TAU;
+
+/// A glow blended into the sky around the direction of the sun
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sun {
+    pub angle: f32, // Radians, measured like the camera's direction (0 = +x, towards +y)
+    pub color: [u8; 3],
+    pub strength: f32, // 0 = no tint, 1 = sun color right at the sun
+}
+
+impl Sun {
+    /// How much of the sun color to blend in looking along `angle`
+    fn glow(&self, angle: f32) -> f32 {
+        let facing = (angle - self.angle).cos().max(0.0);
+        self.strength.clamp(0.0, 1.0) * facing.powi(8)
+    }
+}
+
+/// Panoramic sky drawn above outdoor cells. The texture wraps once around the
+/// full circle of view angles; its bottom row sits on the horizon.
+pub struct Sky {
+    texture: Texture,
+    sun: Option<Sun>,
+}
+
+impl Sky {
+    pub fn new(texture: Texture) -> Self {
+        Self { texture, sun: None }
+    }
+
+    pub fn with_sun(mut self, sun: Option<Sun>) -> Self {
+        self.sun = sun;
+        self
+    }
+
+    /// Sky color looking along `angle` (radians), `v` of the way from the top of
+    /// the panorama (0) down to the horizon (1)
+    pub fn sample(&self, angle: f32, v: f32) -> [u8; 3] {
+        let u = (angle / TAU).rem_euclid(1.0);
+        let tex_x = ((u * self.texture.width as f32) as u32).min(self.texture.width - 1);
+        let tex_y =
+            ((v.clamp(0.0, 1.0) * self.texture.height as f32) as u32).min(self.texture.height - 1);
+        let color = self.texture.get_pixel(tex_x, tex_y);
+        let mut rgb = [
+            ((color >> 24) & 0xFF) as u8,
+            ((color >> 16) & 0xFF) as u8,
+            ((color >> 8) & 0xFF) as u8,
+        ];
+
+        if let Some(sun) = &self.sun {
+            let glow = sun.glow(angle);
+            for (c, s) in rgb.iter_mut().zip(sun.color) {
+                *c = (*c as f32 + (s as f32 - *c as f32) * glow) as u8;
+            }
+        }
+        rgb
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sky_wraps_and_glows_towards_sun() {
+        let mut texture = Texture::new(4, 2);
+        texture.set_pixel(0, 1, 0x102030FF);
+        texture.set_pixel(3, 1, 0x405060FF);
+        let sky = Sky::new(texture);
+        assert_eq!(sky.sample(0.0, 1.0), [0x10, 0x20, 0x30]);
+        assert_eq!(sky.sample(-0.1, 1.0), [0x40, 0x50, 0x60]);
+        assert_eq!(sky.sample(TAU, 1.0), sky.sample(0.0, 1.0));
+
+        let sun = Sun {
+            angle: 0.0,
+            color: [255, 255, 255],
+            strength: 1.0,
+        };
+        let sky = sky.with_sun(Some(sun));
+        assert_eq!(sky.sample(0.0, 1.0), [255, 255, 255]);
+        assert_eq!(sky.sample(TAU / 2.0, 1.0), [0, 0, 0]);
+    }
+}
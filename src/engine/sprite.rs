@@ -0,0 +1,233 @@
+use crate::engine::texture::Texture;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// A named run of sprite frames, `first` to `last` inclusive, shown `fps` times a
+/// second. A clip that doesn't loop holds its last frame, or hands over to `next`.
+/// Each event is reported once when its frame (counted from the clip's start) shows.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Clip {
+    pub first: usize,
+    pub last: usize,
+    pub fps: f32,
+    pub looping: bool,
+    pub next: Option<String>,
+    pub events: Vec<(usize, String)>,
+}
+
+impl Clip {
+    /// A clip that holds one frame
+    pub fn still(frame: usize) -> Self {
+        Self {
+            first: frame,
+            last: frame,
+            fps: 0.0,
+            looping: true,
+            next: None,
+            events: Vec::new(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.last.saturating_sub(self.first) + 1
+    }
+
+    fn events_at(&self, frame: usize) -> impl Iterator<Item = &String> {
+        self.events
+            .iter()
+            .filter(move |(at, _)| *at == frame)
+            .map(|(_, event)| event)
+    }
+}
+
+/// Frames cut from a sprite sheet, and the clips that play them
+#[derive(Clone)]
+pub struct Sprite {
+    frames: Vec<Texture>,
+    clips: HashMap<String, Clip>,
+}
+
+impl Sprite {
+    pub fn from_frames(frames: Vec<Texture>) -> Self {
+        Self {
+            frames,
+            clips: HashMap::new(),
+        }
+    }
+
+    /// Cut `sheet` into `frame_width` by `frame_height` frames, numbered left to right
+    /// and then top to bottom. Partial frames at the right and bottom edges are dropped.
+    pub fn from_sheet(sheet: &Texture, frame_width: u32, frame_height: u32) -> Self {
+        let columns = sheet.width / frame_width.max(1);
+        let rows = sheet.height / frame_height.max(1);
+        let mut frames = Vec::with_capacity((columns * rows) as usize);
+        for row in 0..rows {
+            for column in 0..columns {
+                let mut frame = Texture::new(frame_width, frame_height);
+                for y in 0..frame_height {
+                    for x in 0..frame_width {
+                        let color =
+                            sheet.get_pixel(column * frame_width + x, row * frame_height + y);
+                        frame.set_pixel(x, y, color);
+                    }
+                }
+                frames.push(frame);
+            }
+        }
+        Self::from_frames(frames)
+    }
+
+    pub fn with_clip(mut self, name: &str, clip: Clip) -> Self {
+        self.clips.insert(name.to_string(), clip);
+        self
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn has_clip(&self, name: &str) -> bool {
+        self.clips.contains_key(name)
+    }
+}
+
+/// Plays a sprite's clips. Its owner asks for clips as its state changes; non-looping
+/// clips move on to their `next` by themselves, and frame events come back from `update`.
+#[derive(Clone)]
+pub struct Animator {
+    sprite: Rc<Sprite>,
+    clip: String,
+    frame: usize,        // Counted from the clip's first frame
+    elapsed: f32,        // Seconds the current frame has shown
+    finished: bool,      // A non-looping clip without a next reached its end
+    events: Vec<String>, // Reached since the last update
+}
+
+impl Animator {
+    pub fn new(sprite: Rc<Sprite>, clip: &str) -> Self {
+        let mut animator = Self {
+            sprite,
+            clip: String::new(),
+            frame: 0,
+            elapsed: 0.0,
+            finished: false,
+            events: Vec::new(),
+        };
+        animator.restart(clip);
+        animator
+    }
+
+    pub fn clip(&self) -> &str {
+        &self.clip
+    }
+
+    pub fn has_clip(&self, name: &str) -> bool {
+        self.sprite.has_clip(name)
+    }
+
+    /// Switch to `clip` unless it is already playing. Clips the sprite doesn't have are
+    /// ignored, so owners can ask for optional ones.
+    pub fn play(&mut self, clip: &str) {
+        if self.clip != clip || self.finished {
+            self.restart(clip);
+        }
+    }
+
+    /// Start `clip` from its first frame, even if it is already playing
+    pub fn restart(&mut self, clip: &str) {
+        let Some(started) = self.sprite.clips.get(clip) else {
+            return;
+        };
+        self.events.extend(started.events_at(0).cloned());
+        self.clip = clip.to_string();
+        self.frame = 0;
+        self.elapsed = 0.0;
+        self.finished = false;
+    }
+
+    /// Advance by `dt` seconds, returning the events of every frame reached
+    pub fn update(&mut self, dt: f32) -> Vec<String> {
+        let sprite = Rc::clone(&self.sprite);
+        let Some(clip) = sprite.clips.get(&self.clip) else {
+            return std::mem::take(&mut self.events);
+        };
+        if self.finished || clip.fps <= 0.0 {
+            return std::mem::take(&mut self.events);
+        }
+
+        self.elapsed += dt;
+        let frame_time = 1.0 / clip.fps;
+        while self.elapsed >= frame_time {
+            self.elapsed -= frame_time;
+            if self.frame + 1 < clip.len() {
+                self.frame += 1;
+            } else if clip.looping {
+                self.frame = 0;
+            } else if let Some(next) = &clip.next {
+                // The rest of this tick belongs to the next clip's first frame
+                self.restart(next);
+                break;
+            } else {
+                self.finished = true;
+                break;
+            }
+            self.events.extend(clip.events_at(self.frame).cloned());
+        }
+        std::mem::take(&mut self.events)
+    }
+
+    /// The frame showing now
+    pub fn texture(&self) -> &Texture {
+        let first = self
+            .sprite
+            .clips
+            .get(&self.clip)
+            .map_or(0, |clip| clip.first);
+        let index = (first + self.frame).min(self.sprite.frames.len().saturating_sub(1));
+        &self.sprite.frames[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_animator_plays_clips_and_reports_frame_events() {
+        // A 4x1 sheet of 1x1 frames: 0 is idle, 1-3 fire and shoot on the third frame
+        let mut sheet = Texture::new(4, 1);
+        for x in 0..4 {
+            sheet.set_pixel(x, 0, x);
+        }
+        let sprite = Sprite::from_sheet(&sheet, 1, 1)
+            .with_clip("idle", Clip::still(0))
+            .with_clip(
+                "fire",
+                Clip {
+                    first: 1,
+                    last: 3,
+                    fps: 10.0,
+                    looping: false,
+                    next: Some("idle".to_string()),
+                    events: vec![(2, "shoot".to_string())],
+                },
+            );
+        assert_eq!(sprite.frame_count(), 4);
+
+        let mut animator = Animator::new(Rc::new(sprite), "idle");
+        animator.play("missing");
+        assert_eq!(animator.clip(), "idle");
+
+        animator.play("fire");
+        assert_eq!(animator.texture().get_pixel(0, 0), 1);
+        assert!(animator.update(0.15).is_empty());
+        assert_eq!(animator.texture().get_pixel(0, 0), 2);
+        assert_eq!(animator.update(0.1), vec!["shoot".to_string()]);
+        assert_eq!(animator.texture().get_pixel(0, 0), 3);
+
+        // Past the last frame the fire clip hands back to idle
+        animator.update(0.1);
+        assert_eq!(animator.clip(), "idle");
+        assert_eq!(animator.texture().get_pixel(0, 0), 0);
+    }
+}
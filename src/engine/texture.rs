@@ -1,10 +1,24 @@
-use image::GenericImageView;
-use std::path::Path;
+use crate::assets::Vfs;
+use glam::Vec2;
+use image::DynamicImage;
+use serde::Deserialize;
+
+/// How wall texels are picked. Mip levels are always used; bilinear blends the four
+/// nearest texels of one level and trilinear also blends between the two nearest levels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Filter {
+    Nearest,
+    #[default]
+    Bilinear,
+    Trilinear,
+}
 
 #[derive(Clone)]
 pub struct Texture {
     pub width: u32,
     pub height: u32,
+    pub translucent: bool, // Has pixels with alpha below 255, so walls using it are see-through
     pixels: Vec<u32>,
 }
 
@@ -13,13 +27,24 @@ impl Texture {
         Self {
             width,
             height,
+            translucent: false,
             pixels: vec![0; (width * height) as usize],
         }
     }
 
-    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
-        let img = image::open(path).map_err(|e| format!("Failed to load texture: {}", e))?;
+    /// Decode a texture from an in-memory image file (PNG, etc.)
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let img =
+            image::load_from_memory(bytes).map_err(|e| format!("Failed to load texture: {}", e))?;
+        Ok(Self::from_image(img))
+    }
 
+    /// Load a texture through the virtual filesystem
+    pub fn load_from(vfs: &Vfs, path: &str) -> Result<Self, String> {
+        Self::from_bytes(&vfs.read(path)?)
+    }
+
+    fn from_image(img: DynamicImage) -> Self {
         // Convert to RGBA8
         let img = img.to_rgba8();
         let (width, height) = img.dimensions();
@@ -34,11 +59,14 @@ impl Texture {
             pixels.push((r << 24) | (g << 16) | (b << 8) | a);
         }
 
-        Ok(Self {
+        let translucent = pixels.iter().any(|pixel| pixel & 0xFF < 0xFF);
+
+        Self {
             width,
             height,
+            translucent,
             pixels,
-        })
+        }
     }
 
     pub fn get_pixel(&self, x: u32, y: u32) -> u32 {
@@ -48,40 +76,191 @@ impl Texture {
         self.pixels[(y * self.width + x) as usize]
     }
 
-    pub fn get_pixel_scaled(&self, x: f32, y: f32) -> u32 {
-        // Scale x and y to texture coordinates
-        let tx = ((x * self.width as f32) as u32) % self.width;
-        let ty = ((y * self.height as f32) as u32) % self.height;
-        self.get_pixel(tx, ty)
-    }
-
     pub fn set_pixel(&mut self, x: u32, y: u32, color: u32) {
         if x < self.width && y < self.height {
             self.pixels[(y * self.width + x) as usize] = color;
         }
     }
+
+    /// Blend of the four texels around `u`, `v` (0..1, repeating)
+    pub fn get_pixel_bilinear(&self, u: f32, v: f32) -> u32 {
+        // Texel centers sit at half coordinates
+        let x = u.rem_euclid(1.0) * self.width as f32 - 0.5;
+        let y = v.rem_euclid(1.0) * self.height as f32 - 0.5;
+        let (fx, fy) = (x - x.floor(), y - y.floor());
+        let wrap = |value: f32, size: u32| (value.floor() as i64).rem_euclid(size as i64) as u32;
+        let (x0, y0) = (wrap(x, self.width), wrap(y, self.height));
+        let (x1, y1) = ((x0 + 1) % self.width, (y0 + 1) % self.height);
+
+        let top = mix(self.get_pixel(x0, y0), self.get_pixel(x1, y0), fx);
+        let bottom = mix(self.get_pixel(x0, y1), self.get_pixel(x1, y1), fx);
+        mix(top, bottom, fy)
+    }
+
+    /// The next mip level down: half the size, each texel the average of the four it covers
+    fn downsample(&self) -> Self {
+        let (width, height) = ((self.width / 2).max(1), (self.height / 2).max(1));
+        let mut smaller = Self::new(width, height);
+        smaller.translucent = self.translucent;
+        for y in 0..height {
+            for x in 0..width {
+                let (sx, sy) = (x * 2, y * 2);
+                let (sx1, sy1) = ((sx + 1).min(self.width - 1), (sy + 1).min(self.height - 1));
+                let top = mix(self.get_pixel(sx, sy), self.get_pixel(sx1, sy), 0.5);
+                let bottom = mix(self.get_pixel(sx, sy1), self.get_pixel(sx1, sy1), 0.5);
+                smaller.set_pixel(x, y, mix(top, bottom, 0.5));
+            }
+        }
+        smaller
+    }
+}
+
+/// Blend two RGBA colors channel by channel, `t` of the way from `a` to `b`
+fn mix(a: u32, b: u32, t: f32) -> u32 {
+    (0..4).fold(0, |color, channel| {
+        let shift = channel * 8;
+        let from = ((a >> shift) & 0xFF) as f32;
+        let to = ((b >> shift) & 0xFF) as f32;
+        let value = (from + (to - from) * t).round() as u32;
+        color | (value.min(0xFF) << shift)
+    })
+}
+
+/// A texture and its mip levels, full size first, down to a single texel
+struct MipChain {
+    levels: Vec<Texture>,
+}
+
+impl MipChain {
+    fn build(texture: Texture) -> Self {
+        let mut levels = vec![texture];
+        loop {
+            let last = &levels[levels.len() - 1];
+            if last.width <= 1 && last.height <= 1 {
+                break;
+            }
+            let next = last.downsample();
+            levels.push(next);
+        }
+        Self { levels }
+    }
+
+    fn level(&self, level: usize) -> &Texture {
+        &self.levels[level.min(self.levels.len() - 1)]
+    }
+}
+
+/// How a wall texture moves: frames cycle `fps` times a second, and the image slides
+/// `scroll` texture lengths a second along u and v
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Animation {
+    pub fps: f32,
+    pub scroll: Vec2,
+}
+
+impl Animation {
+    /// Which of `frames` frames shows `time` seconds in
+    pub fn frame(&self, time: f32, frames: usize) -> usize {
+        if self.fps <= 0.0 || frames == 0 {
+            return 0;
+        }
+        (time * self.fps).max(0.0) as usize % frames
+    }
+
+    /// How far the texture has slid `time` seconds in, wrapped to one texture length
+    pub fn offset(&self, time: f32) -> Vec2 {
+        (self.scroll * time).rem_euclid(Vec2::ONE)
+    }
+}
+
+struct WallTexture {
+    frames: Vec<MipChain>,
+    animation: Animation,
 }
 
 pub struct TextureCache {
-    textures: Vec<Texture>,
+    textures: Vec<WallTexture>,
+    base_mip: usize, // Lower texture quality skips the largest levels
+    filter: Filter,
+    time: f32, // Seconds of game time, for animation
 }
 
 impl TextureCache {
     pub fn new() -> Self {
         Self {
             textures: Vec::new(),
+            base_mip: 0,
+            filter: Filter::default(),
+            time: 0.0,
         }
     }
 
-    pub fn load_texture<P: AsRef<Path>>(&mut self, path: P) -> Result<usize, String> {
-        let texture = Texture::load(path)?;
+    pub fn set_base_mip(&mut self, level: usize) {
+        self.base_mip = level;
+    }
+
+    pub fn set_filter(&mut self, filter: Filter) {
+        self.filter = filter;
+    }
+
+    pub fn set_time(&mut self, time: f32) {
+        self.time = time;
+    }
+
+    /// Load a texture from its frames, one for a texture that doesn't animate
+    pub fn load_texture<S: AsRef<str>>(
+        &mut self,
+        vfs: &Vfs,
+        frames: &[S],
+        animation: Animation,
+    ) -> Result<usize, String> {
+        if frames.is_empty() {
+            return Err("Texture has no frames".to_string());
+        }
+        let frames = frames
+            .iter()
+            .map(|path| Texture::load_from(vfs, path.as_ref()).map(MipChain::build))
+            .collect::<Result<Vec<MipChain>, String>>()?;
         let index = self.textures.len();
-        self.textures.push(texture);
+        self.textures.push(WallTexture { frames, animation });
         Ok(index)
     }
 
+    /// The frame of texture `index` showing right now
+    fn current_frame(&self, index: usize) -> Option<&MipChain> {
+        let texture = self.textures.get(index)?;
+        let frame = texture.animation.frame(self.time, texture.frames.len());
+        Some(&texture.frames[frame])
+    }
+
+    /// The current frame of a texture at its base level for the current quality
     pub fn get_texture(&self, index: usize) -> Option<&Texture> {
-        self.textures.get(index)
+        self.current_frame(index)
+            .map(|chain| chain.level(self.base_mip))
+    }
+
+    /// Sample the current frame of texture `index` at `u`, `v` (0..1, repeating) where one screen pixel
+    /// covers `footprint` texels of the base level. The mip level follows the footprint.
+    pub fn sample(&self, index: usize, u: f32, v: f32, footprint: f32) -> Option<u32> {
+        let chain = self.current_frame(index)?;
+        let scrolled = self.textures[index].animation.offset(self.time);
+        let (u, v) = (u - scrolled.x, v - scrolled.y);
+        let lod = self.base_mip as f32 + footprint.max(1.0).log2();
+        let nearest = |texture: &Texture| {
+            let x = ((u.rem_euclid(1.0) * texture.width as f32) as u32).min(texture.width - 1);
+            let y = ((v.rem_euclid(1.0) * texture.height as f32) as u32).min(texture.height - 1);
+            texture.get_pixel(x, y)
+        };
+        let color = match self.filter {
+            Filter::Nearest => nearest(chain.level(lod.round() as usize)),
+            Filter::Bilinear => chain.level(lod.round() as usize).get_pixel_bilinear(u, v),
+            Filter::Trilinear => {
+                let finer = chain.level(lod.floor() as usize).get_pixel_bilinear(u, v);
+                let coarser = chain.level(lod.ceil() as usize).get_pixel_bilinear(u, v);
+                mix(finer, coarser, lod.fract())
+            }
+        };
+        Some(color)
     }
 
     pub fn clear(&mut self) {
@@ -94,3 +273,46 @@ impl Default for TextureCache {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mip_chain_averages_down_to_one_texel() {
+        // A 4x2 checker of black and white averages to mid grey
+        let mut texture = Texture::new(4, 2);
+        for y in 0..2 {
+            for x in 0..4 {
+                let white = (x + y) % 2 == 0;
+                texture.set_pixel(x, y, if white { 0xFFFFFFFF } else { 0x000000FF });
+            }
+        }
+        let chain = MipChain::build(texture);
+        let sizes: Vec<(u32, u32)> = chain.levels.iter().map(|t| (t.width, t.height)).collect();
+        assert_eq!(sizes, vec![(4, 2), (2, 1), (1, 1)]);
+        assert_eq!(chain.level(5).get_pixel(0, 0), 0x808080FF);
+
+        // Halfway between two texel centers blends them evenly
+        let row = chain.level(0);
+        assert_eq!(row.get_pixel_bilinear(0.25, 0.25), 0x808080FF);
+        assert_eq!(row.get_pixel_bilinear(0.125, 0.25), 0xFFFFFFFF);
+    }
+
+    #[test]
+    fn test_animation_cycles_frames_and_wraps_scroll() {
+        let animation = Animation {
+            fps: 4.0,
+            scroll: Vec2::new(0.5, 0.0),
+        };
+        assert_eq!(animation.frame(0.1, 3), 0);
+        assert_eq!(animation.frame(0.5, 3), 2);
+        assert_eq!(animation.frame(1.0, 3), 1);
+        let still = Animation {
+            fps: 0.0,
+            scroll: Vec2::ZERO,
+        };
+        assert_eq!(still.frame(10.0, 3), 0);
+        assert_eq!(animation.offset(3.0), Vec2::new(0.5, 0.0));
+    }
+}
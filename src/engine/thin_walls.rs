@@ -0,0 +1,154 @@
+use glam::Vec2;
+
+fn cross(a: Vec2, b: Vec2) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+/// Where a ray crosses the segment from `start` to `end`, as (distance along the ray
+/// in multiples of `direction`, position along the segment 0..1)
+pub fn ray_segment(origin: Vec2, direction: Vec2, start: Vec2, end: Vec2) -> Option<(f32, f32)> {
+    let edge = end - start;
+    let denom = cross(direction, edge);
+    if denom.abs() < 1e-6 {
+        return None; // Parallel
+    }
+    let to_start = start - origin;
+    let distance = cross(to_start, edge) / denom;
+    let along = cross(to_start, direction) / denom;
+    (distance > 0.0 && (0.0..=1.0).contains(&along)).then_some((distance, along))
+}
+
+/// A wall segment inside a single map cell, such as a fence, a window or a diagonal wall
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThinWall {
+    pub start: Vec2,
+    pub end: Vec2,
+    pub texture: i32, // Wall ID, same numbering as the map layout
+    pub solid: bool,  // Blocks movement and projectiles
+}
+
+impl ThinWall {
+    pub fn new(start: Vec2, end: Vec2, texture: i32) -> Self {
+        Self {
+            start,
+            end,
+            texture,
+            solid: true,
+        }
+    }
+
+    /// Where a ray hits the wall, as (distance along the ray, position along the wall 0..1)
+    pub fn intersect(&self, origin: Vec2, direction: Vec2) -> Option<(f32, f32)> {
+        ray_segment(origin, direction, self.start, self.end)
+    }
+
+    pub fn distance_to(&self, point: Vec2) -> f32 {
+        let edge = self.end - self.start;
+        let t = ((point - self.start).dot(edge) / edge.length_squared()).clamp(0.0, 1.0);
+        point.distance(self.start + edge * t)
+    }
+
+    /// Walls running along x face north/south and get the same side shading as grid walls
+    pub fn faces_y(&self) -> bool {
+        let edge = self.end - self.start;
+        edge.x.abs() > edge.y.abs()
+    }
+}
+
+/// Thin walls bucketed by the cells they sit in, so rays only test walls in cells they cross
+#[derive(Debug, Clone)]
+pub struct ThinWalls {
+    width: usize,
+    height: usize,
+    cells: Vec<Vec<ThinWall>>,
+}
+
+impl ThinWalls {
+    pub fn empty() -> Self {
+        Self {
+            width: 0,
+            height: 0,
+            cells: Vec::new(),
+        }
+    }
+
+    pub fn new(width: usize, height: usize, walls: Vec<ThinWall>) -> Self {
+        let mut cells = vec![Vec::new(); width * height];
+        for wall in walls {
+            // Walls longer than a cell are listed in every cell they pass through
+            let samples = ((wall.end - wall.start).length() * 4.0).ceil().max(1.0) as usize;
+            let mut listed = Vec::new();
+            for i in 0..=samples {
+                let point = wall.start.lerp(wall.end, i as f32 / samples as f32);
+                let (x, y) = (point.x.floor() as usize, point.y.floor() as usize);
+                if x < width && y < height && !listed.contains(&(x, y)) {
+                    listed.push((x, y));
+                    cells[y * width + x].push(wall.clone());
+                }
+            }
+        }
+        Self {
+            width,
+            height,
+            cells,
+        }
+    }
+
+    pub fn in_cell(&self, x: i32, y: i32) -> &[ThinWall] {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return &[];
+        }
+        &self.cells[y as usize * self.width + x as usize]
+    }
+
+    fn near(&self, point: Vec2) -> impl Iterator<Item = &ThinWall> {
+        let (cx, cy) = (point.x.floor() as i32, point.y.floor() as i32);
+        (-1..=1)
+            .flat_map(move |dy| (-1..=1).map(move |dx| (cx + dx, cy + dy)))
+            .flat_map(move |(x, y)| self.in_cell(x, y))
+            .filter(|wall| wall.solid)
+    }
+
+    /// Whether moving from `from` to `to` brings a body of `radius` into a solid wall.
+    /// Moving away from a wall is always allowed, so bodies can't get stuck on one.
+    pub fn blocks_move(&self, from: Vec2, to: Vec2, radius: f32) -> bool {
+        self.near(to).any(|wall| {
+            let distance = wall.distance_to(to);
+            distance < radius && distance < wall.distance_to(from)
+        })
+    }
+
+    /// First point where the segment from `from` to `to` crosses a solid wall
+    pub fn crossing(&self, from: Vec2, to: Vec2) -> Option<Vec2> {
+        let step = to - from;
+        self.near(to)
+            .filter_map(|wall| wall.intersect(from, step))
+            .map(|(t, _)| t)
+            .filter(|&t| t <= 1.0)
+            .min_by(|a, b| a.total_cmp(b))
+            .map(|t| from + step * t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ray_hits_diagonal_wall() {
+        let wall = ThinWall::new(Vec2::new(2.0, 0.0), Vec2::new(3.0, 1.0), 1);
+        let (distance, along) = wall.intersect(Vec2::new(0.0, 0.5), Vec2::X).unwrap();
+        assert_eq!(distance, 2.5);
+        assert_eq!(along, 0.5);
+        assert!(wall.intersect(Vec2::new(0.0, 0.5), -Vec2::X).is_none());
+
+        let walls = ThinWalls::new(4, 2, vec![wall]);
+        assert_eq!(walls.in_cell(2, 0).len(), 1);
+        assert!(walls
+            .crossing(Vec2::new(2.0, 0.5), Vec2::new(2.9, 0.5))
+            .is_some());
+        assert!(walls
+            .crossing(Vec2::new(2.0, 0.5), Vec2::new(2.4, 0.5))
+            .is_none());
+    }
+}
@@ -1,5 +1,10 @@
+use crate::engine::portals::Portals;
+use crate::engine::sprite::{Animator, Sprite};
 use crate::engine::texture::Texture;
+use crate::game::ai::perception::Perception;
+use crate::game::content::{DEATH_CLIP, FIRE_CLIP, GIB_CLIP, IDLE_CLIP, PAIN_CLIP, SHOOT_EVENT};
 use glam::Vec2;
+use std::rc::Rc;
 use std::time::Instant;
 
 #[derive(Clone, Debug)]
@@ -9,12 +14,17 @@ pub enum AIState {
     Chase,
     Attack,
     Retreat,
+    Search, // Heading for where the player was last seen or heard, then looking around
 }
 
 pub enum EnemyType {
     Melee {
         damage: i32,
         attack_range: f32,
+        windup: f32, // Seconds from starting a swing to the blow landing
+        last_attack: Instant,
+        attack_interval: f32,
+        swing: Option<f32>, // Seconds until the blow of the current swing lands
     },
     Ranged {
         damage: i32,
@@ -22,65 +32,135 @@ pub enum EnemyType {
         projectile_speed: f32,
         last_shot: Instant,
         shoot_interval: f32,
-        idle_texture: Texture,
-        fire_texture: Texture,
-        firing: bool,
     },
 }
 
+const PAIN_TIME: f32 = 0.25; // Seconds a hit stops an enemy in its tracks
+const MELEE_REACH: f32 = 1.2; // A blow lands on a player this far past the attack range
+const MELEE_KNOCKBACK: f32 = 0.4; // Map units a blow shoves the player
+const SEARCH_TIME: f32 = 8.0; // Seconds spent on a search before going back to patrolling
+const SEARCH_TURN: f32 = 1.5; // Radians per second while looking around
+const RALLY_DISTANCE: f32 = 1.0; // A retreating enemy stops this close to the ally it falls back to
+
+/// Something an enemy did this update that the game carries out
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EnemyAction {
+    /// Fire a projectile from `position`
+    Shoot {
+        position: Vec2,
+        velocity: Vec2,
+        damage: i32,
+    },
+    /// A melee blow hit the player, who gets shoved by `knockback`
+    Melee { damage: i32, knockback: Vec2 },
+}
+
+/// What a hit did to an enemy, for its sound hook
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Hurt {
+    Pain,
+    Death,
+    Gibbed,
+}
+
+impl Hurt {
+    /// Sound hook name, as in `enemy.<archetype>.<hook>`
+    pub fn hook(self) -> &'static str {
+        match self {
+            Hurt::Pain => "pain",
+            Hurt::Death => "death",
+            Hurt::Gibbed => "gib",
+        }
+    }
+}
+
 pub struct Enemy {
     pub position: Vec2,
     pub direction: Vec2,
     pub health: i32,
     pub state: AIState,
-    pub texture: Texture,
+    animator: Animator,
     pub speed: f32,
     pub chase_range: f32,
+    pub gib_threshold: Option<i32>, // Overkill damage that gibs instead of killing
     enemy_type: EnemyType,
+    pub perception: Perception,
+    pub squad: Option<String>, // Enemies in the same squad share what they know of the player
+    pub flank: Option<Vec2>,   // Side of the player to close in from, set by the squads
+    pub rally: Option<Vec2>,   // Ally to fall back to while retreating, set by the squads
+    pain_time: f32,            // Seconds of flinching left
+    search_time: f32,          // Seconds of looking around left once at the last known position
+    corpse: bool,              // Died with a death clip, so it stays where it fell
+    sees_player: bool,         // Saw the player on its last update
+    calling: bool,             // Just spotted the player and calls out to its allies
     pub patrol_points: Vec<Vec2>,
     current_patrol_point: usize,
+    pub archetype: String, // Content definition this enemy was created from
 }
 
 impl Enemy {
-    pub fn new_melee(position: Vec2, texture: Texture) -> Self {
+    pub fn new_melee(position: Vec2, sprite: Rc<Sprite>) -> Self {
         Self {
             position,
             direction: Vec2::new(1.0, 0.0),
             health: 100,
             state: AIState::Idle,
-            texture,
+            animator: Animator::new(sprite, IDLE_CLIP),
             speed: 2.0,
             chase_range: 5.0,
+            gib_threshold: None,
+            perception: Perception::new(),
+            squad: None,
+            flank: None,
+            rally: None,
+            pain_time: 0.0,
+            search_time: SEARCH_TIME,
+            corpse: false,
+            sees_player: false,
+            calling: false,
             enemy_type: EnemyType::Melee {
                 damage: 10,
                 attack_range: 1.0,
+                windup: 0.4,
+                last_attack: Instant::now(),
+                attack_interval: 1.0,
+                swing: None,
             },
             patrol_points: vec![],
             current_patrol_point: 0,
+            archetype: String::new(),
         }
     }
 
-    pub fn new_ranged(position: Vec2, idle_texture: Texture, fire_texture: Texture) -> Self {
+    pub fn new_ranged(position: Vec2, sprite: Rc<Sprite>) -> Self {
         Self {
             position,
             direction: Vec2::new(1.0, 0.0),
             health: 50,
             state: AIState::Idle,
-            texture: idle_texture.clone(),
+            animator: Animator::new(sprite, IDLE_CLIP),
             speed: 2.0,
             chase_range: 10.0,
+            gib_threshold: None,
+            perception: Perception::new(),
+            squad: None,
+            flank: None,
+            rally: None,
+            pain_time: 0.0,
+            search_time: SEARCH_TIME,
+            corpse: false,
+            sees_player: false,
+            calling: false,
             enemy_type: EnemyType::Ranged {
                 damage: 5,
                 attack_range: 8.0,
                 projectile_speed: 8.0,
                 last_shot: Instant::now(),
                 shoot_interval: 1.0,
-                idle_texture,
-                fire_texture,
-                firing: false,
             },
             patrol_points: vec![],
             current_patrol_point: 0,
+            archetype: String::new(),
         }
     }
 
@@ -89,39 +169,47 @@ impl Enemy {
         self.state = AIState::Patrol;
     }
 
-    fn can_see_player(&self, player_pos: Vec2, map: &[Vec<i32>]) -> bool {
-        let to_player = player_pos - self.position;
-        let distance = to_player.length();
-
-        // Check if player is within range
-        if distance > self.chase_range {
-            return false;
-        }
-
-        // Ray cast to check line of sight
-        let direction = to_player.normalize();
-        let steps = (distance * 2.0) as usize;
-        let step_size = distance / steps as f32;
-
-        for i in 0..=steps {
-            let t = i as f32 * step_size;
-            let point = self.position + direction * t;
-
-            let x = point.x.floor() as usize;
-            let y = point.y.floor() as usize;
-
-            // Check if we hit a wall before reaching the player
-            if x >= map[0].len() || y >= map.len() || map[y][x] != 0 {
-                return false;
+    /// Override the default stats for this enemy's type
+    pub fn set_stats(
+        &mut self,
+        health: i32,
+        damage: i32,
+        speed: f32,
+        attack_range: f32,
+        chase_range: f32,
+    ) {
+        self.health = health;
+        self.speed = speed;
+        self.chase_range = chase_range;
+        match &mut self.enemy_type {
+            EnemyType::Melee {
+                damage: d,
+                attack_range: r,
+                ..
             }
-
-            // Check if we reached the player
-            if (point - player_pos).length() < 0.5 {
-                return true;
+            | EnemyType::Ranged {
+                damage: d,
+                attack_range: r,
+                ..
+            } => {
+                *d = damage;
+                *r = attack_range;
             }
         }
+    }
 
-        false
+    /// Where the player appears to this enemy, if it can see them. Through a
+    /// window portal that isn't where they really are.
+    fn sight_of_player(
+        &self,
+        player_pos: Vec2,
+        map: &[Vec<i32>],
+        portals: &Portals,
+    ) -> Option<Vec2> {
+        // Ray cast to check line of sight, then check the player is within range
+        portals
+            .seen_at(self.position, player_pos, map)
+            .filter(|seen| seen.distance(self.position) <= self.chase_range)
     }
 
     pub fn update(
@@ -129,23 +217,79 @@ impl Enemy {
         player_pos: Vec2,
         dt: f32,
         map: &[Vec<i32>],
-    ) -> Option<(Vec2, Vec2, i32)> {
-        let mut should_shoot = None;
+        portals: &Portals,
+        player_light: f32,
+    ) -> Option<EnemyAction> {
+        let mut action = None;
+
+        // Corpses only play out their death, and a flinch holds everything else up
+        if !self.is_alive() {
+            self.sees_player = false;
+            self.animator.update(dt);
+            return None;
+        }
+        if self.pain_time > 0.0 {
+            self.pain_time -= dt;
+            // Without a pain clip a shot being fired carries on, and still goes off
+            let events = self.animator.update(dt);
+            if self.pain_time <= 0.0 && self.animator.clip() == PAIN_CLIP {
+                self.animator.play(IDLE_CLIP);
+            }
+            return self.shot(&events);
+        }
+
+        // Aim and walk at where the player appears, so chases follow them through windows
+        let sighting = self.sight_of_player(player_pos, map, portals);
+        let in_plain_sight = sighting == Some(player_pos); // Not through a window
 
-        // Update direction to face player or patrol point
+        // Once fighting, an enemy keeps track of a player it can see. Before that it
+        // has to look their way long enough to notice them.
+        let fighting = matches!(
+            self.state,
+            AIState::Chase | AIState::Attack | AIState::Retreat
+        );
+        let sees_player = if fighting {
+            sighting.is_some()
+        } else {
+            let view = sighting
+                .filter(|&seen| self.perception.in_view(self.position, self.direction, seen))
+                .map(|seen| {
+                    let closeness = 1.0 - seen.distance(self.position) / self.chase_range;
+                    (closeness, player_light)
+                });
+            self.perception.watch(view, dt)
+        };
+        let sighting = sighting.filter(|_| sees_player);
+        self.sees_player = sees_player;
+        if sees_player && !fighting {
+            self.calling = true;
+        }
+        if sighting.is_some() {
+            self.perception.awareness = 1.0;
+            self.perception.last_known = sighting;
+        }
+        let player_pos = sighting.unwrap_or(player_pos);
+
+        // Update direction to face player, patrol point or where the player was
         let target_pos = match self.state {
             AIState::Chase | AIState::Attack => player_pos,
             AIState::Patrol if !self.patrol_points.is_empty() => {
                 self.patrol_points[self.current_patrol_point]
             }
+            AIState::Search => self
+                .perception
+                .last_known
+                .unwrap_or(self.position + self.direction),
             _ => self.position + self.direction,
         };
-        self.direction = (target_pos - self.position).normalize();
+        if let Some(direction) = (target_pos - self.position).try_normalize() {
+            self.direction = direction;
+        }
 
         match self.state {
             AIState::Idle => {
                 // Check if player is visible
-                if self.can_see_player(player_pos, map) {
+                if sees_player {
                     self.state = AIState::Chase;
                 }
             }
@@ -165,7 +309,7 @@ impl Enemy {
                 }
 
                 // Check if player is visible
-                if self.can_see_player(player_pos, map) {
+                if sees_player {
                     self.state = AIState::Chase;
                 }
             }
@@ -173,16 +317,19 @@ impl Enemy {
                 let to_player = player_pos - self.position;
                 let distance = to_player.length();
 
-                if !self.can_see_player(player_pos, map) {
-                    self.state = AIState::Patrol;
+                if !sees_player {
+                    // Lost them; go and look where they were last seen
+                    self.start_search();
                 } else {
                     match &mut self.enemy_type {
                         EnemyType::Melee { attack_range, .. } => {
                             if distance < *attack_range {
                                 self.state = AIState::Attack;
                             } else {
-                                // Move towards player
-                                self.move_towards(player_pos, dt, map);
+                                // Move towards player, from the side the squads picked
+                                let range = *attack_range * 0.8;
+                                let approach = self.approach(player_pos, range, map);
+                                self.move_towards(approach, dt, map);
                             }
                         }
                         EnemyType::Ranged { attack_range, .. } => {
@@ -199,8 +346,9 @@ impl Enemy {
                                         self.position = away;
                                     }
                                 } else {
-                                    // Move towards player
-                                    self.move_towards(player_pos, dt, map);
+                                    // Move towards player, from the side the squads picked
+                                    let approach = self.approach(player_pos, optimal_range, map);
+                                    self.move_towards(approach, dt, map);
                                 }
                             }
                         }
@@ -211,59 +359,76 @@ impl Enemy {
                 let to_player = player_pos - self.position;
                 let distance = to_player.length();
 
-                if !self.can_see_player(player_pos, map) {
+                if !sees_player {
                     self.state = AIState::Chase;
                 } else {
                     match &mut self.enemy_type {
-                        EnemyType::Melee { attack_range, .. } => {
+                        EnemyType::Melee {
+                            attack_range,
+                            last_attack,
+                            attack_interval,
+                            windup,
+                            swing,
+                            ..
+                        } => {
                             if distance > *attack_range * 1.2 {
                                 self.state = AIState::Chase;
+                            } else if swing.is_none()
+                                && last_attack.elapsed().as_secs_f32() >= *attack_interval
+                            {
+                                // Wind up; the blow lands when the swing is done
+                                *last_attack = Instant::now();
+                                *swing = Some(*windup);
+                                self.animator.restart(FIRE_CLIP);
                             }
                         }
                         EnemyType::Ranged {
                             attack_range,
-                            damage,
-                            projectile_speed,
                             last_shot,
                             shoot_interval,
-                            idle_texture,
-                            fire_texture,
-                            firing,
+                            ..
                         } => {
                             if distance > *attack_range * 1.2 {
                                 self.state = AIState::Chase;
-                                *firing = false;
-                                self.texture = idle_texture.clone();
                             } else {
                                 // Try to shoot
                                 let now = Instant::now();
                                 if now.duration_since(*last_shot).as_secs_f32() >= *shoot_interval {
                                     *last_shot = now;
-                                    *firing = true;
-                                    self.texture = fire_texture.clone();
-
-                                    // Compute projectile velocity
-                                    let direction = to_player.normalize();
-                                    should_shoot = Some((
-                                        self.position,
-                                        direction * *projectile_speed,
-                                        *damage,
-                                    ));
-                                } else if *firing
-                                    && now.duration_since(*last_shot).as_secs_f32() >= 0.1
-                                {
-                                    // Reset firing animation
-                                    *firing = false;
-                                    self.texture = idle_texture.clone();
+                                    self.animator.restart(FIRE_CLIP);
                                 }
                             }
                         }
                     }
                 }
             }
+            AIState::Search => {
+                self.search_time -= dt;
+                if sees_player {
+                    self.state = AIState::Chase;
+                } else if self.search_time <= 0.0 {
+                    // Give up and go back to the patrol
+                    self.perception.last_known = None;
+                    self.state = AIState::Patrol;
+                } else if let Some(target) = self.perception.last_known {
+                    if self.position.distance(target) > 0.3 {
+                        self.move_towards(target, dt, map);
+                    } else {
+                        self.perception.last_known = None;
+                    }
+                } else {
+                    // Look around
+                    self.direction = Vec2::from_angle(SEARCH_TURN * dt).rotate(self.direction);
+                }
+            }
             AIState::Retreat => {
                 if self.health > 50 {
                     self.state = AIState::Chase;
+                } else if let Some(rally) = self.rally {
+                    // Fall back to an ally
+                    if self.position.distance(rally) > RALLY_DISTANCE {
+                        self.move_towards(rally, dt, map);
+                    }
                 } else {
                     // Move away from player
                     let away_from_player = self.position - player_pos;
@@ -275,7 +440,61 @@ impl Enemy {
             }
         }
 
-        should_shoot
+        // Walking into a portal carries the enemy through it
+        if let Some(fold) = portals.travel(self.position) {
+            self.position = fold.point(self.position);
+            self.direction = fold.direction(self.direction);
+        }
+
+        // A finished swing hits if the player is still in reach and in front
+        if let EnemyType::Melee {
+            damage,
+            attack_range,
+            swing,
+            ..
+        } = &mut self.enemy_type
+        {
+            if let Some(left) = swing {
+                *left -= dt;
+            }
+            if swing.is_some_and(|left| left <= 0.0) {
+                *swing = None;
+                let to_player = player_pos - self.position;
+                let in_reach = to_player.length() <= *attack_range * MELEE_REACH;
+                let in_front = to_player.normalize_or_zero().dot(self.direction) > 0.5;
+                if in_plain_sight && in_reach && in_front {
+                    action = Some(EnemyAction::Melee {
+                        damage: *damage,
+                        knockback: to_player.normalize_or_zero() * MELEE_KNOCKBACK,
+                    });
+                }
+            }
+        }
+
+        let events = self.animator.update(dt);
+        self.shot(&events).or(action)
+    }
+
+    /// The shot a ranged enemy fires if its fire clip reached the shoot frame
+    fn shot(&self, events: &[String]) -> Option<EnemyAction> {
+        let shoots = events.iter().any(|event| event == SHOOT_EVENT);
+        match &self.enemy_type {
+            EnemyType::Ranged {
+                damage,
+                projectile_speed,
+                ..
+            } if shoots => Some(EnemyAction::Shoot {
+                position: self.position,
+                velocity: self.direction * *projectile_speed,
+                damage: *damage,
+            }),
+            _ => None,
+        }
+    }
+
+    /// The sprite frame showing now
+    pub fn texture(&self) -> &Texture {
+        self.animator.texture()
     }
 
     fn move_towards(&mut self, target: Vec2, dt: f32, map: &[Vec<i32>]) {
@@ -290,6 +509,15 @@ impl Enemy {
         }
     }
 
+    /// Where to close in on the player from: `range` out on the flank the squads
+    /// picked, or straight at them without one or when that spot is in a wall
+    fn approach(&self, player_pos: Vec2, range: f32, map: &[Vec<i32>]) -> Vec2 {
+        self.flank
+            .map(|flank| player_pos + flank * range)
+            .filter(|&spot| !self.check_collision(spot, map))
+            .unwrap_or(player_pos)
+    }
+
     fn check_collision(&self, pos: Vec2, map: &[Vec<i32>]) -> bool {
         let map_x = pos.x.floor() as usize;
         let map_y = pos.y.floor() as usize;
@@ -297,17 +525,99 @@ impl Enemy {
         map_x >= map[0].len() || map_y >= map.len() || map[map_y][map_x] != 0
     }
 
-    pub fn take_damage(&mut self, amount: i32) {
+    /// Apply a hit. Corpses take no damage, so this returns None for them.
+    pub fn take_damage(&mut self, amount: i32) -> Option<Hurt> {
+        if !self.is_alive() {
+            return None;
+        }
         self.health -= amount;
+
+        if !self.is_alive() {
+            self.sees_player = false;
+            let overkill = self.gib_threshold.is_some_and(|gib| self.health <= -gib);
+            let (hurt, clip) = if overkill && self.animator.has_clip(GIB_CLIP) {
+                (Hurt::Gibbed, GIB_CLIP)
+            } else {
+                (Hurt::Death, DEATH_CLIP)
+            };
+            self.corpse = self.animator.has_clip(clip);
+            self.animator.restart(clip);
+            return Some(hurt);
+        }
+
+        // Getting shot gives the player away, if not where they are
+        self.perception.awareness = 1.0;
         if self.health < 40 {
             self.state = AIState::Retreat;
+        } else if matches!(self.state, AIState::Idle | AIState::Patrol) {
+            self.start_search();
+        }
+        // A flinch spoils a swing
+        if let EnemyType::Melee { swing, .. } = &mut self.enemy_type {
+            *swing = None;
+        }
+        if self.animator.has_clip(PAIN_CLIP) {
+            self.animator.restart(PAIN_CLIP);
         }
+        self.pain_time = PAIN_TIME;
+        Some(Hurt::Pain)
     }
 
     pub fn is_alive(&self) -> bool {
         self.health > 0
     }
 
+    /// Hear a noise from `source` at `strength` (0..1). Unless it's already fighting,
+    /// the enemy goes to see what it was.
+    pub fn hear(&mut self, source: Vec2, strength: f32) {
+        if !self.is_alive() {
+            return;
+        }
+        self.perception.hear(source, strength);
+        if matches!(
+            self.state,
+            AIState::Idle | AIState::Patrol | AIState::Search
+        ) {
+            self.start_search();
+        }
+    }
+
+    /// An ally told this enemy where the player is. Unless it's already fighting,
+    /// it goes to look.
+    pub fn alert(&mut self, player_pos: Vec2) {
+        if !self.is_alive() {
+            return;
+        }
+        self.perception.awareness = 1.0;
+        self.perception.last_known = Some(player_pos);
+        if matches!(
+            self.state,
+            AIState::Idle | AIState::Patrol | AIState::Search
+        ) {
+            self.start_search();
+        }
+    }
+
+    /// Whether the enemy saw the player on its last update
+    pub fn sees_player(&self) -> bool {
+        self.sees_player
+    }
+
+    /// Whether the enemy has just spotted the player and calls out, once per sighting
+    pub fn take_call(&mut self) -> bool {
+        std::mem::take(&mut self.calling)
+    }
+
+    fn start_search(&mut self) {
+        self.state = AIState::Search;
+        self.search_time = SEARCH_TIME;
+    }
+
+    /// Dead and with no corpse to leave behind
+    pub fn is_gone(&self) -> bool {
+        !self.is_alive() && !self.corpse
+    }
+
     pub fn get_damage(&self) -> i32 {
         match &self.enemy_type {
             EnemyType::Melee { damage, .. } => *damage,
@@ -322,3 +632,93 @@ impl Enemy {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::sprite::Clip;
+
+    #[test]
+    fn test_death_leaves_corpse_only_with_death_clip() {
+        let frames = vec![Texture::new(1, 1); 3];
+        let plain =
+            Rc::new(Sprite::from_frames(frames.clone()).with_clip(IDLE_CLIP, Clip::still(0)));
+        let dying = Rc::new(
+            Sprite::from_frames(frames)
+                .with_clip(IDLE_CLIP, Clip::still(0))
+                .with_clip(DEATH_CLIP, Clip::still(1))
+                .with_clip(GIB_CLIP, Clip::still(2)),
+        );
+
+        let mut enemy = Enemy::new_ranged(Vec2::new(1.5, 1.5), Rc::clone(&dying));
+        enemy.gib_threshold = Some(30);
+        assert_eq!(enemy.take_damage(10), Some(Hurt::Pain));
+        assert_eq!(enemy.take_damage(40), Some(Hurt::Death));
+        assert!(!enemy.is_alive() && !enemy.is_gone());
+        assert_eq!(enemy.take_damage(100), None);
+
+        let mut gibbed = Enemy::new_melee(Vec2::new(1.5, 1.5), Rc::clone(&dying));
+        gibbed.gib_threshold = Some(30);
+        assert_eq!(gibbed.take_damage(200), Some(Hurt::Gibbed));
+
+        let mut vanishing = Enemy::new_ranged(Vec2::new(1.5, 1.5), Rc::clone(&plain));
+        assert_eq!(vanishing.take_damage(50), Some(Hurt::Death));
+        assert!(vanishing.is_gone());
+
+        // Without a pain clip, a flinch mid-shot still lets the shot go
+        let firing = Sprite::from_frames(vec![Texture::new(1, 1); 3])
+            .with_clip(IDLE_CLIP, Clip::still(0))
+            .with_clip(
+                FIRE_CLIP,
+                Clip {
+                    first: 1,
+                    last: 2,
+                    fps: 10.0,
+                    looping: false,
+                    next: Some(IDLE_CLIP.to_string()),
+                    events: vec![(1, SHOOT_EVENT.to_string())],
+                },
+            );
+        let mut shooter = Enemy::new_ranged(Vec2::new(1.5, 1.5), Rc::new(firing));
+        shooter.animator.restart(FIRE_CLIP);
+        assert_eq!(shooter.take_damage(10), Some(Hurt::Pain));
+        let map = vec![vec![0; 3]; 3];
+        let action = shooter.update(Vec2::ZERO, 0.15, &map, &Portals::empty(), 1.0);
+        assert!(matches!(action, Some(EnemyAction::Shoot { .. })));
+    }
+
+    #[test]
+    fn test_melee_swing_lands_after_windup() {
+        let map = vec![
+            vec![1, 1, 1, 1, 1],
+            vec![1, 0, 0, 0, 1],
+            vec![1, 1, 1, 1, 1],
+        ];
+        let sprite = Rc::new(
+            Sprite::from_frames(vec![Texture::new(1, 1)]).with_clip(IDLE_CLIP, Clip::still(0)),
+        );
+        let mut enemy = Enemy::new_melee(Vec2::new(1.5, 1.5), sprite);
+        enemy.perception.awareness = 1.0;
+        if let EnemyType::Melee { last_attack, .. } = &mut enemy.enemy_type {
+            *last_attack = Instant::now() - std::time::Duration::from_secs(2);
+        }
+        let player = Vec2::new(2.3, 1.5);
+        let portals = Portals::empty();
+
+        // Spot the player, close in, then start swinging
+        assert_eq!(enemy.update(player, 0.1, &map, &portals, 1.0), None);
+        assert_eq!(enemy.update(player, 0.1, &map, &portals, 1.0), None);
+        assert!(matches!(enemy.state, AIState::Attack));
+        assert_eq!(enemy.update(player, 0.1, &map, &portals, 1.0), None);
+
+        // Nothing lands until the wind-up is over
+        assert_eq!(enemy.update(player, 0.2, &map, &portals, 1.0), None);
+        match enemy.update(player, 0.2, &map, &portals, 1.0) {
+            Some(EnemyAction::Melee { damage, knockback }) => {
+                assert_eq!(damage, 10);
+                assert!(knockback.x > 0.0);
+            }
+            other => panic!("expected a melee hit, got {:?}", other),
+        }
+    }
+}
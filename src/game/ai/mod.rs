@@ -1,6 +1,10 @@
 pub mod enemy;
+pub mod perception;
+pub mod squad;
 
-pub use enemy::{AIState, Enemy, EnemyType};
+pub use enemy::{AIState, Enemy, EnemyAction, EnemyType, Hurt};
+pub use perception::Noise;
+pub use squad::Squads;
 
 use glam::Vec2;
 
@@ -0,0 +1,165 @@
+use crate::engine::portals::Portals;
+use glam::Vec2;
+use std::collections::{HashMap, VecDeque};
+
+const DEFAULT_FOV: f32 = 1.75; // Radians, about 100 degrees
+const AWARENESS_RATE: f32 = 1.5; // Per second, for a lit player right up close
+const AWARENESS_DECAY: f32 = 0.3; // Per second with the player out of view
+const MIN_LIGHT: f32 = 0.1; // Even pitch darkness doesn't hide a player forever
+const HEARING_GAIN: f32 = 0.5; // Awareness a noise adds at full strength
+
+/// What an enemy knows of the player. Seeing them in its view cone builds awareness
+/// until it spots them, faster when they are lit and close; noises add to it.
+#[derive(Debug, Clone)]
+pub struct Perception {
+    pub fov: f32,                 // Radians across the view cone
+    pub awareness: f32,           // 0 unaware .. 1 spotted
+    pub last_known: Option<Vec2>, // Where the player was last seen or heard
+}
+
+impl Perception {
+    pub fn new() -> Self {
+        Self {
+            fov: DEFAULT_FOV,
+            awareness: 0.0,
+            last_known: None,
+        }
+    }
+
+    /// Whether `target` is inside the view cone of something at `eye` facing `facing`
+    pub fn in_view(&self, eye: Vec2, facing: Vec2, target: Vec2) -> bool {
+        let to_target = (target - eye).normalize_or_zero();
+        to_target == Vec2::ZERO || to_target.dot(facing) >= (self.fov / 2.0).cos()
+    }
+
+    /// Build awareness while the player is in view, with how close they are (0 at the
+    /// edge of sight range to 1 right here) and how lit; let it fade otherwise.
+    /// Returns whether the player is spotted.
+    pub fn watch(&mut self, view: Option<(f32, f32)>, dt: f32) -> bool {
+        match view {
+            Some((closeness, light)) => {
+                let closeness = closeness.clamp(0.0, 1.0);
+                let rate = AWARENESS_RATE * light.max(MIN_LIGHT) * (0.5 + 1.5 * closeness);
+                self.awareness = (self.awareness + rate * dt).min(1.0);
+            }
+            None => self.awareness = (self.awareness - AWARENESS_DECAY * dt).max(0.0),
+        }
+        self.awareness >= 1.0
+    }
+
+    /// Hear a noise from `source` at `strength` (0..1)
+    pub fn hear(&mut self, source: Vec2, strength: f32) {
+        self.awareness = (self.awareness + strength * HEARING_GAIN).min(1.0);
+        self.last_known = Some(source);
+    }
+}
+
+impl Default for Perception {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A sound the player made that enemies can hear. It carries `loudness` cells through
+/// open cells, so a wall in the way makes it go the long way round, and portals carry
+/// it on to the cells they link to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Noise {
+    pub position: Vec2,
+    pub loudness: f32,
+}
+
+impl Noise {
+    pub fn new(position: Vec2, loudness: f32) -> Self {
+        Self { position, loudness }
+    }
+
+    /// How loud the noise is in every cell it reaches, 1 at its source fading to 0
+    pub fn spread(&self, map: &[Vec<i32>], portals: &Portals) -> HashMap<(i32, i32), f32> {
+        let is_open = |(x, y): (i32, i32)| {
+            y >= 0
+                && x >= 0
+                && map
+                    .get(y as usize)
+                    .and_then(|row| row.get(x as usize))
+                    .is_some_and(|&tile| tile == 0)
+        };
+
+        let start = (
+            self.position.x.floor() as i32,
+            self.position.y.floor() as i32,
+        );
+        let mut reached = HashMap::new();
+        if !is_open(start) {
+            return reached;
+        }
+
+        // Breadth-first, so each cell gets its shortest path through open cells
+        let mut queue = VecDeque::from([(start, 0)]);
+        reached.insert(start, 1.0);
+        while let Some(((x, y), steps)) = queue.pop_front() {
+            let next_steps = steps + 1;
+            if next_steps as f32 >= self.loudness {
+                continue;
+            }
+            let linked = portals.in_cell(x, y).map(|portal| portal.to);
+            for next in [(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)]
+                .into_iter()
+                .chain(linked)
+            {
+                if is_open(next) && !reached.contains_key(&next) {
+                    reached.insert(next, 1.0 - next_steps as f32 / self.loudness);
+                    queue.push_back((next, next_steps));
+                }
+            }
+        }
+        reached
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::portals::Portal;
+
+    #[test]
+    fn test_noise_goes_around_walls_and_awareness_builds_in_view() {
+        // The wall between the two ends makes the noise go around through the bottom row
+        let map = vec![
+            vec![1, 1, 1, 1, 1],
+            vec![1, 0, 1, 0, 1],
+            vec![1, 0, 0, 0, 1],
+            vec![1, 1, 1, 1, 1],
+        ];
+        let none = Portals::empty();
+        let heard = Noise::new(Vec2::new(1.5, 1.5), 6.0).spread(&map, &none);
+        assert_eq!(heard[&(1, 1)], 1.0);
+        assert_eq!(heard[&(3, 1)], 1.0 - 4.0 / 6.0);
+        assert!(!heard.contains_key(&(2, 1)));
+        let quiet = Noise::new(Vec2::new(1.5, 1.5), 2.0);
+        assert!(!quiet.spread(&map, &none).contains_key(&(3, 1)));
+
+        // A portal carries the noise straight across to the cell it links to
+        let teleporter = Portal {
+            from: (1, 1),
+            to: (3, 1),
+            turns: 0,
+            window: false,
+        };
+        let portals = Portals::new(5, 4, vec![teleporter]);
+        assert_eq!(quiet.spread(&map, &portals)[&(3, 1)], 0.5);
+
+        let mut perception = Perception::new();
+        let facing = Vec2::X;
+        assert!(perception.in_view(Vec2::ZERO, facing, Vec2::new(3.0, 1.0)));
+        assert!(!perception.in_view(Vec2::ZERO, facing, Vec2::new(-3.0, 0.0)));
+
+        // A lit player up close is spotted well before a dim one far away
+        assert!(!perception.watch(Some((1.0, 1.0)), 0.2));
+        assert!(perception.watch(Some((1.0, 1.0)), 0.2));
+        let mut dim = Perception::new();
+        assert!(!dim.watch(Some((0.0, 0.2)), 0.4));
+        assert!(!dim.watch(None, 1.0));
+        assert_eq!(dim.awareness, 0.0);
+    }
+}
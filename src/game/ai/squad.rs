@@ -0,0 +1,223 @@
+use crate::engine::portals::Portals;
+use crate::game::ai::enemy::{AIState, Enemy};
+use crate::game::ai::perception::Noise;
+use glam::Vec2;
+use std::collections::HashMap;
+use std::f32::consts::TAU;
+
+const CALL_LOUDNESS: f32 = 8.0; // Open cells an enemy's call to its allies carries
+const FLANK_ANGLE: f32 = 0.9; // Radians between neighbouring attackers around the player
+const SEARCHED: f32 = 1.0; // Map units from the squad's last sighting that count as having looked there
+
+/// The enemies' shared blackboard. Each frame, before they update, it passes on what
+/// they know of the player: an enemy that spots them calls out to the allies in earshot,
+/// squads keep each other up to date on where the player is, attackers get spread
+/// around the player and retreating enemies get an ally to fall back to.
+#[derive(Debug, Clone, Default)]
+pub struct Squads {
+    last_known: HashMap<String, Vec2>, // Where each squad last saw the player, until it gives up
+}
+
+impl Squads {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn coordinate(
+        &mut self,
+        enemies: &mut [Enemy],
+        player_pos: Vec2,
+        map: &[Vec<i32>],
+        portals: &Portals,
+    ) {
+        // Squads pool what their members see
+        let mut sighted = Vec::new();
+        for enemy in enemies.iter().filter(|enemy| enemy.sees_player()) {
+            if let (Some(squad), Some(position)) = (&enemy.squad, enemy.perception.last_known) {
+                if self.last_known.insert(squad.clone(), position) != Some(position) {
+                    sighted.push(squad.clone());
+                }
+            }
+        }
+
+        // Members who can't see the player themselves are sent where the squad last saw
+        // them whenever that changes, and searchers who know of nowhere to look go there
+        for enemy in enemies.iter_mut().filter(|enemy| !enemy.sees_player()) {
+            let Some(squad) = &enemy.squad else {
+                continue;
+            };
+            let Some(&position) = self.last_known.get(squad) else {
+                continue;
+            };
+            let lost = matches!(enemy.state, AIState::Search)
+                && enemy.perception.last_known.is_none()
+                && enemy.position.distance(position) > SEARCHED;
+            if sighted.contains(squad) || lost {
+                enemy.alert(position);
+            }
+        }
+
+        // A squad gives up once none of its members are after the player any more
+        self.last_known.retain(|squad, _| {
+            enemies.iter().any(|enemy| {
+                enemy.is_alive()
+                    && enemy.squad.as_ref() == Some(squad)
+                    && !matches!(enemy.state, AIState::Idle | AIState::Patrol)
+            })
+        });
+
+        // A call carries through open cells and portals like the player's noises do
+        for caller in 0..enemies.len() {
+            if !enemies[caller].take_call() {
+                continue;
+            }
+            let Some(position) = enemies[caller].perception.last_known else {
+                continue;
+            };
+            let heard = Noise::new(enemies[caller].position, CALL_LOUDNESS).spread(map, portals);
+            for (i, enemy) in enemies.iter_mut().enumerate() {
+                let cell = enemy.position.floor();
+                if i != caller && heard.contains_key(&(cell.x as i32, cell.y as i32)) {
+                    enemy.alert(position);
+                }
+            }
+        }
+
+        // Attackers close in from either side of each other instead of queueing up
+        let attackers: Vec<usize> = (0..enemies.len())
+            .filter(|&i| {
+                let enemy = &enemies[i];
+                enemy.sees_player() && matches!(enemy.state, AIState::Chase | AIState::Attack)
+            })
+            .collect();
+        let bearings: Vec<Vec2> = attackers
+            .iter()
+            .map(|&i| (enemies[i].position - player_pos).normalize_or_zero())
+            .collect();
+        for enemy in enemies.iter_mut() {
+            enemy.flank = None;
+        }
+        for (&i, flank) in attackers.iter().zip(flanks(&bearings)) {
+            enemies[i].flank = Some(flank);
+        }
+
+        // Retreating enemies fall back to the nearest ally still in the fight
+        for i in 0..enemies.len() {
+            let enemy = &enemies[i];
+            let rally = if matches!(enemy.state, AIState::Retreat) && enemy.is_alive() {
+                enemies
+                    .iter()
+                    .filter(|ally| ally.is_alive() && !matches!(ally.state, AIState::Retreat))
+                    .map(|ally| ally.position)
+                    .min_by(|a, b| {
+                        let (a, b) = (a.distance(enemy.position), b.distance(enemy.position));
+                        a.total_cmp(&b)
+                    })
+            } else {
+                None
+            };
+            enemies[i].rally = rally;
+        }
+    }
+}
+
+/// Spread attackers coming at the player from `bearings` (unit directions from the
+/// player) evenly about their average, keeping their order around the player so
+/// their paths don't cross. Returns the direction each should close in from.
+pub fn flanks(bearings: &[Vec2]) -> Vec<Vec2> {
+    let Some(&first) = bearings.first() else {
+        return Vec::new();
+    };
+    let middle = bearings
+        .iter()
+        .sum::<Vec2>()
+        .try_normalize()
+        .unwrap_or(first);
+    let step = FLANK_ANGLE.min(TAU / bearings.len() as f32);
+
+    let mut order: Vec<usize> = (0..bearings.len()).collect();
+    order.sort_by(|&a, &b| {
+        let (a, b) = (
+            middle.angle_between(bearings[a]),
+            middle.angle_between(bearings[b]),
+        );
+        a.total_cmp(&b)
+    });
+
+    let mut flanks = vec![Vec2::ZERO; bearings.len()];
+    let centre = (bearings.len() - 1) as f32 / 2.0;
+    for (slot, &i) in order.iter().enumerate() {
+        flanks[i] = Vec2::from_angle((slot as f32 - centre) * step).rotate(middle);
+    }
+    flanks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::sprite::{Clip, Sprite};
+    use crate::engine::texture::Texture;
+    use crate::game::content::IDLE_CLIP;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_spotting_alerts_allies_and_attackers_spread_out() {
+        // Two rooms joined at the bottom; the far end of the right room is out of earshot
+        let mut map = vec![vec![1; 16]; 5];
+        for row in map.iter_mut().take(4).skip(1) {
+            for cell in row.iter_mut().take(15).skip(1) {
+                *cell = 0;
+            }
+        }
+        map[1][5] = 1;
+        map[2][5] = 1;
+        let sprite = Rc::new(
+            Sprite::from_frames(vec![Texture::new(1, 1)]).with_clip(IDLE_CLIP, Clip::still(0)),
+        );
+        let enemy = |x: f32, y: f32| Enemy::new_melee(Vec2::new(x, y), Rc::clone(&sprite));
+
+        let mut spotter = enemy(1.5, 1.5);
+        spotter.perception.awareness = 1.0;
+        spotter.squad = Some("guards".to_string());
+        let mut squadmate = enemy(14.5, 1.5);
+        squadmate.squad = Some("guards".to_string());
+        let mut enemies = vec![spotter, squadmate, enemy(6.5, 3.5), enemy(12.5, 3.5)];
+
+        let player = Vec2::new(3.5, 1.5);
+        enemies[0].update(player, 0.01, &map, &Portals::empty(), 1.0);
+        assert!(enemies[0].sees_player());
+
+        let mut squads = Squads::new();
+        squads.coordinate(&mut enemies, player, &map, &Portals::empty());
+        assert_eq!(squads.last_known.get("guards"), Some(&player));
+        // The squadmate is told however far away it is, the others only if they hear the call
+        assert!(matches!(enemies[1].state, AIState::Search));
+        assert_eq!(enemies[1].perception.last_known, Some(player));
+        assert!(matches!(enemies[2].state, AIState::Search));
+        assert!(matches!(enemies[3].state, AIState::Idle));
+
+        // A searcher that has lost track is sent back where the squad last saw the player
+        enemies[1].perception.last_known = None;
+        squads.coordinate(&mut enemies, player, &map, &Portals::empty());
+        assert_eq!(enemies[1].perception.last_known, Some(player));
+
+        // A lone attacker keeps its own side; two on one line split up
+        let alone = flanks(&[Vec2::X]);
+        assert!(alone[0].abs_diff_eq(Vec2::X, 1e-5));
+        let pair = flanks(&[Vec2::X, Vec2::X]);
+        assert!((pair[0].angle_between(pair[1]).abs() - FLANK_ANGLE).abs() < 1e-5);
+
+        // Badly hurt, the spotter falls back to the nearest ally
+        enemies[0].take_damage(70);
+        squads.coordinate(&mut enemies, player, &map, &Portals::empty());
+        assert_eq!(enemies[0].rally, Some(Vec2::new(6.5, 3.5)));
+
+        // With the spotter dead and the rest back on patrol, the squad forgets the player
+        enemies[0].take_damage(100);
+        for enemy in &mut enemies {
+            enemy.state = AIState::Patrol;
+        }
+        squads.coordinate(&mut enemies, player, &map, &Portals::empty());
+        assert!(squads.last_known.is_empty());
+    }
+}
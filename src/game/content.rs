@@ -0,0 +1,301 @@
+use crate::assets::Vfs;
+use crate::engine::sprite::{Clip, Sprite};
+use crate::engine::texture::Texture;
+use anyhow::Result;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+pub const CONTENT_PATH: &str = "assets/content.toml";
+
+// Clips enemies and weapons ask their sprites for, and the event that lets a shot go
+pub const IDLE_CLIP: &str = "idle";
+pub const FIRE_CLIP: &str = "fire";
+pub const SHOOT_EVENT: &str = "shoot";
+// Optional clips: a flinch when hurt, and the death (or overkill gibbing) that leaves
+// the corpse. Enemies without a death clip vanish when they die.
+pub const PAIN_CLIP: &str = "pain";
+pub const DEATH_CLIP: &str = "death";
+pub const GIB_CLIP: &str = "gib";
+const FIRE_FRAME_TIME: f32 = 0.1; // How long a plain fire texture shows
+
+fn default_clip_fps() -> f32 {
+    10.0
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FrameEventDef {
+    pub frame: usize, // Counted from the clip's first frame
+    pub event: String,
+}
+
+/// A clip over sheet frames `frames[0]` to `frames[1]`
+#[derive(Debug, Clone, Deserialize)]
+pub struct ClipDef {
+    pub frames: [usize; 2],
+    #[serde(default = "default_clip_fps")]
+    pub fps: f32,
+    #[serde(default)]
+    pub looping: bool,
+    pub next: Option<String>,
+    #[serde(default)]
+    pub events: Vec<FrameEventDef>,
+}
+
+/// A sprite sheet cut into `frame_size` frames, with named clips. Enemies play "idle"
+/// and "fire", and shoot on a "shoot" event; weapons do the same.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SpriteDef {
+    pub sheet: String,
+    pub frame_size: [u32; 2],
+    pub clips: HashMap<String, ClipDef>,
+}
+
+impl SpriteDef {
+    pub fn to_sprite(&self, sheet: &Texture) -> Result<Sprite> {
+        let [width, height] = self.frame_size;
+        if width == 0 || height == 0 || width > sheet.width || height > sheet.height {
+            anyhow::bail!(
+                "Frame size {}x{} doesn't fit a {}x{} sheet",
+                width,
+                height,
+                sheet.width,
+                sheet.height
+            );
+        }
+        if !self.clips.contains_key(IDLE_CLIP) {
+            anyhow::bail!("Sprite needs an '{}' clip", IDLE_CLIP);
+        }
+
+        let mut sprite = Sprite::from_sheet(sheet, width, height);
+        for (name, def) in &self.clips {
+            let [first, last] = def.frames;
+            if first > last || last >= sprite.frame_count() {
+                anyhow::bail!(
+                    "Clip '{}' frames {}-{} are outside the sheet's {} frames",
+                    name,
+                    first,
+                    last,
+                    sprite.frame_count()
+                );
+            }
+            if let Some(next) = def
+                .next
+                .as_ref()
+                .filter(|next| !self.clips.contains_key(*next))
+            {
+                anyhow::bail!("Clip '{}' goes on to unknown clip '{}'", name, next);
+            }
+            if let Some(event) = def.events.iter().find(|event| event.frame > last - first) {
+                anyhow::bail!("Clip '{}' has event '{}' past its end", name, event.event);
+            }
+            let clip = Clip {
+                first,
+                last,
+                fps: def.fps,
+                looping: def.looping,
+                next: def.next.clone(),
+                events: def
+                    .events
+                    .iter()
+                    .map(|event| (event.frame, event.event.clone()))
+                    .collect(),
+            };
+            sprite = sprite.with_clip(name, clip);
+        }
+        Ok(sprite)
+    }
+}
+
+/// Two textures as a sprite: idle, and fire showing briefly as the shot goes
+pub fn two_frame_sprite(idle_texture: Texture, fire_texture: Texture) -> Sprite {
+    let fire = Clip {
+        first: 1,
+        last: 1,
+        fps: 1.0 / FIRE_FRAME_TIME,
+        looping: false,
+        next: Some(IDLE_CLIP.to_string()),
+        events: vec![(0, SHOOT_EVENT.to_string())],
+    };
+    Sprite::from_frames(vec![idle_texture, fire_texture])
+        .with_clip(IDLE_CLIP, Clip::still(0))
+        .with_clip(FIRE_CLIP, fire)
+}
+
+fn default_kind() -> String {
+    "ranged".to_string()
+}
+
+/// Enemy archetype referenced by the `type` of map enemies.
+/// Stats left out here fall back to the built-in defaults for the kind,
+/// and per-enemy `properties` in a map override them again. A `sprite` sheet
+/// replaces the idle and fire textures, which stay as the fallback. A hit leaving
+/// health at or below minus `gib_threshold` gibs the enemy instead of killing it.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EnemyArchetypeDef {
+    #[serde(default = "default_kind")]
+    pub kind: String, // "ranged" or "melee"
+    pub idle_texture: String,
+    pub fire_texture: Option<String>,
+    pub attack_sound: Option<String>,
+    pub pain_sound: Option<String>,
+    pub death_sound: Option<String>,
+    pub gib_sound: Option<String>,
+    pub health: Option<i32>,
+    pub damage: Option<i32>,
+    pub speed: Option<f32>,
+    pub attack_range: Option<f32>,
+    pub chase_range: Option<f32>,
+    pub gib_threshold: Option<i32>,
+    pub sprite: Option<SpriteDef>,
+}
+
+fn default_weapon_damage() -> i32 {
+    20
+}
+
+fn default_cooldown() -> f32 {
+    0.5
+}
+
+fn default_projectile_speed() -> f32 {
+    10.0
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WeaponDef {
+    pub idle_texture: String,
+    pub fire_texture: String,
+    pub sound: Option<String>,
+    #[serde(default = "default_weapon_damage")]
+    pub damage: i32,
+    #[serde(default = "default_cooldown")]
+    pub cooldown: f32,
+    #[serde(default = "default_projectile_speed")]
+    pub projectile_speed: f32,
+    pub sprite: Option<SpriteDef>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PlayerContentDef {
+    pub weapon: Option<String>,
+}
+
+/// Definitions from `assets/content.toml` or a mod manifest
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ContentDef {
+    #[serde(default)]
+    pub player: PlayerContentDef,
+    #[serde(default)]
+    pub enemies: HashMap<String, EnemyArchetypeDef>,
+    #[serde(default)]
+    pub weapons: HashMap<String, WeaponDef>,
+}
+
+/// Enemy archetypes and weapons merged from the base game and enabled mods
+#[derive(Debug, Default)]
+pub struct Content {
+    pub player_weapon: Option<String>,
+    pub enemies: HashMap<String, EnemyArchetypeDef>,
+    pub weapons: HashMap<String, WeaponDef>,
+    sources: HashMap<String, String>, // "enemy:<name>" / "weapon:<name>" -> who defined it
+}
+
+impl Content {
+    pub fn load(vfs: &Vfs) -> Result<Self> {
+        let text = vfs
+            .read_to_string(CONTENT_PATH)
+            .map_err(anyhow::Error::msg)?;
+        let def: ContentDef = toml::from_str(&text)?;
+
+        let mut content = Self::default();
+        content.merge(def, "base game");
+        Ok(content)
+    }
+
+    /// Layer `def` on top of the current definitions. Returns a message for
+    /// every definition that replaced one from another mod.
+    pub fn merge(&mut self, def: ContentDef, source: &str) -> Vec<String> {
+        let mut conflicts = Vec::new();
+
+        if let Some(weapon) = def.player.weapon {
+            self.player_weapon = Some(weapon);
+        }
+
+        for (name, enemy) in def.enemies {
+            if let Some(previous) = self.claim(format!("enemy:{}", name), source) {
+                conflicts.push(format!(
+                    "Enemy archetype '{}' from {} is overridden by {}",
+                    name, previous, source
+                ));
+            }
+            self.enemies.insert(name, enemy);
+        }
+
+        for (name, weapon) in def.weapons {
+            if let Some(previous) = self.claim(format!("weapon:{}", name), source) {
+                conflicts.push(format!(
+                    "Weapon '{}' from {} is overridden by {}",
+                    name, previous, source
+                ));
+            }
+            self.weapons.insert(name, weapon);
+        }
+
+        conflicts
+    }
+
+    /// Record `source` as the owner of `key`, returning the previous owner if it
+    /// was another mod. Overriding the base game is the point of a mod, not a conflict.
+    fn claim(&mut self, key: String, source: &str) -> Option<String> {
+        let previous = self.sources.insert(key, source.to_string())?;
+        (previous != "base game" && previous != source).then_some(previous)
+    }
+
+    /// Every texture and sound path the definitions reference
+    pub fn asset_paths(&self) -> (Vec<&str>, Vec<&str>) {
+        let mut textures = Vec::new();
+        let mut sounds = Vec::new();
+
+        for enemy in self.enemies.values() {
+            textures.push(enemy.idle_texture.as_str());
+            textures.extend(enemy.fire_texture.as_deref());
+            sounds.extend(enemy.attack_sound.as_deref());
+            sounds.extend(enemy.pain_sound.as_deref());
+            sounds.extend(enemy.death_sound.as_deref());
+            sounds.extend(enemy.gib_sound.as_deref());
+            textures.extend(enemy.sprite.as_ref().map(|sprite| sprite.sheet.as_str()));
+        }
+        for weapon in self.weapons.values() {
+            textures.push(weapon.idle_texture.as_str());
+            textures.push(weapon.fire_texture.as_str());
+            sounds.extend(weapon.sound.as_deref());
+            textures.extend(weapon.sprite.as_ref().map(|sprite| sprite.sheet.as_str()));
+        }
+
+        (textures, sounds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mod_overrides_are_reported_between_mods() {
+        let mut vfs = Vfs::new();
+        vfs.mount_dir(".", 0);
+        let mut content = Content::load(&vfs).unwrap();
+        assert!(content.enemies.contains_key("ranged"));
+
+        let tweak: ContentDef = toml::from_str(
+            "[enemies.ranged]\nidle_texture = \"a.png\"\nhealth = 80\n[weapons.shotgun]\nidle_texture = \"b.png\"\nfire_texture = \"c.png\"",
+        )
+        .unwrap();
+        assert!(content.merge(tweak.clone(), "tweaks").is_empty());
+        assert_eq!(content.enemies["ranged"].health, Some(80));
+        assert_eq!(content.weapons["shotgun"].damage, 20);
+
+        let conflicts = content.merge(tweak, "other");
+        assert_eq!(conflicts.len(), 2);
+    }
+}
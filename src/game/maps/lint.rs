@@ -0,0 +1,326 @@
+use crate::engine::camera::{CROUCH_EYE_HEIGHT, HEAD_ROOM};
+use crate::engine::grid;
+use crate::engine::heights::HeightMap;
+use crate::game::maps::MapFile;
+use glam::Vec2;
+use std::collections::VecDeque;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// One problem found in a map, optionally tied to a grid cell
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub cell: Option<(i32, i32)>,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        match self.cell {
+            Some((x, y)) => write!(f, "{} at ({}, {}): {}", severity, x, y, self.message),
+            None => write!(f, "{}: {}", severity, self.message),
+        }
+    }
+}
+
+pub fn has_errors(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics.iter().any(|d| d.severity == Severity::Error)
+}
+
+/// Check a parsed map for problems the loader doesn't catch: an open border,
+/// entities placed inside walls, unreachable patrol points and wall IDs
+/// without a texture. `wall_textures` is how many wall textures are loaded.
+pub fn lint(map_file: &MapFile, wall_textures: usize) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let map = &map_file.map.layout;
+    let (width, height) = (map_file.map.width, map_file.map.height);
+    if width == 0 || height == 0 {
+        diagnostics.push(Diagnostic {
+            severity: Severity::Error,
+            cell: None,
+            message: "map has no cells".to_string(),
+        });
+        return diagnostics;
+    }
+
+    let mut report = |severity, cell: Option<(i32, i32)>, message: String| {
+        diagnostics.push(Diagnostic {
+            severity,
+            cell,
+            message,
+        })
+    };
+
+    // Wall IDs and the border. Rays that leave through a gap never hit anything.
+    for (y, row) in map.iter().enumerate() {
+        for (x, &tile) in row.iter().enumerate() {
+            let cell = Some((x as i32, y as i32));
+            let on_border = x == 0 || y == 0 || x == width - 1 || y == height - 1;
+
+            let mirror = map_file.mirrors.iter().any(|mirror| mirror.wall == tile);
+            if tile < 0 {
+                report(Severity::Error, cell, format!("invalid wall ID {}", tile));
+            } else if tile as usize > wall_textures && mirror {
+                // Mirrors only need a texture once rays run out of bounces
+                report(
+                    Severity::Warning,
+                    cell,
+                    format!(
+                        "mirror wall ID {} has no texture past the bounce limit",
+                        tile
+                    ),
+                );
+            } else if tile as usize > wall_textures {
+                report(
+                    Severity::Error,
+                    cell,
+                    format!(
+                        "wall ID {} has no texture (only {} loaded)",
+                        tile, wall_textures
+                    ),
+                );
+            }
+            if on_border && tile == 0 {
+                report(Severity::Error, cell, "gap in the map border".to_string());
+            }
+        }
+    }
+
+    // Thin walls sit inside empty cells and use the same wall textures
+    for thin_wall in &map_file.thin_walls {
+        let [x, y] = thin_wall.cell;
+        let cell = Some((x, y));
+        let inside = x >= 0 && y >= 0 && (x as usize) < width && (y as usize) < height;
+        if !inside {
+            report(
+                Severity::Error,
+                cell,
+                "thin wall is outside the map".to_string(),
+            );
+        } else if map[y as usize][x as usize] != 0 {
+            report(
+                Severity::Error,
+                cell,
+                "thin wall is inside a wall".to_string(),
+            );
+        }
+        if thin_wall.texture as usize > wall_textures {
+            report(
+                Severity::Error,
+                cell,
+                format!(
+                    "thin wall ID {} has no texture (only {} loaded)",
+                    thin_wall.texture, wall_textures
+                ),
+            );
+        }
+    }
+
+    // Portals link empty cells, and arriving on another portal would bounce straight back
+    for portal in &map_file.portals {
+        for (end, [x, y]) in [("portal", portal.cell), ("portal target", portal.target)] {
+            let inside = x >= 0 && y >= 0 && (x as usize) < width && (y as usize) < height;
+            if !inside {
+                report(
+                    Severity::Error,
+                    Some((x, y)),
+                    format!("{} is outside the map", end),
+                );
+            } else if map[y as usize][x as usize] != 0 {
+                report(
+                    Severity::Error,
+                    Some((x, y)),
+                    format!("{} is inside a wall", end),
+                );
+            }
+        }
+        if map_file
+            .portals
+            .iter()
+            .any(|other| other.cell == portal.target)
+        {
+            let [x, y] = portal.cell;
+            report(
+                Severity::Error,
+                Some((x, y)),
+                "portal leads onto another portal".to_string(),
+            );
+        }
+    }
+
+    // Height layers: open cells need room to stand
+    let heights = HeightMap::from_layers(
+        width,
+        height,
+        map_file.map.floor_heights.as_deref(),
+        map_file.map.ceiling_heights.as_deref(),
+        map_file.map.wall_heights.as_deref(),
+    )
+    .with_sky(map_file.map.outdoor.as_deref());
+    for (y, row) in map.iter().enumerate() {
+        for (x, _) in row.iter().enumerate().filter(|(_, &tile)| tile == 0) {
+            let (x, y) = (x as i32, y as i32);
+            if heights.ceiling(x, y) - heights.floor(x, y) < CROUCH_EYE_HEIGHT + HEAD_ROOM {
+                report(
+                    Severity::Warning,
+                    Some((x, y)),
+                    "ceiling is too low to crouch under".to_string(),
+                );
+            }
+        }
+    }
+
+    let mut cells = (0..height as i32).flat_map(|y| (0..width as i32).map(move |x| (x, y)));
+    if map_file.sky.is_none() && cells.any(|(x, y)| heights.is_outdoor(x, y)) {
+        report(
+            Severity::Warning,
+            None,
+            "outdoor cells without a [sky] show the fog color".to_string(),
+        );
+    }
+
+    let cell_of = |pos: Vec2| (pos.x.floor() as i32, pos.y.floor() as i32);
+
+    let spawn: Vec2 = map_file.player.spawn.into();
+    if grid::is_blocked(spawn, map) {
+        report(
+            Severity::Error,
+            Some(cell_of(spawn)),
+            "player spawn is inside a wall or outside the map".to_string(),
+        );
+    }
+
+    for (i, enemy) in map_file.enemies.iter().enumerate() {
+        let position: Vec2 = enemy.position.into();
+        if grid::is_blocked(position, map) {
+            report(
+                Severity::Error,
+                Some(cell_of(position)),
+                format!("enemy {} is inside a wall or outside the map", i),
+            );
+            continue;
+        }
+
+        let reachable = reachable_cells(position, map);
+        let mut previous = position;
+        for point in enemy.patrol_points.iter().map(|&p| Vec2::from(p)) {
+            let (x, y) = cell_of(point);
+            if grid::is_blocked(point, map) {
+                report(
+                    Severity::Error,
+                    Some((x, y)),
+                    format!("patrol point of enemy {} is inside a wall", i),
+                );
+            } else if !reachable[y as usize][x as usize] {
+                report(
+                    Severity::Error,
+                    Some((x, y)),
+                    format!("patrol point of enemy {} can't be reached", i),
+                );
+            } else if !grid::line_of_sight(previous, point, map) {
+                // Enemies walk straight at their next patrol point
+                report(
+                    Severity::Warning,
+                    Some((x, y)),
+                    format!(
+                        "enemy {} has no straight path to this patrol point and may get stuck",
+                        i
+                    ),
+                );
+            }
+            previous = point;
+        }
+    }
+
+    diagnostics
+}
+
+/// Flood fill the empty cells connected to `start`
+fn reachable_cells(start: Vec2, map: &[Vec<i32>]) -> Vec<Vec<bool>> {
+    let mut reachable = vec![vec![false; map[0].len()]; map.len()];
+    let start = (start.x.floor() as usize, start.y.floor() as usize);
+    reachable[start.1][start.0] = true;
+
+    let mut queue = VecDeque::from([start]);
+    while let Some((x, y)) = queue.pop_front() {
+        let neighbours = [
+            (x.wrapping_sub(1), y),
+            (x + 1, y),
+            (x, y.wrapping_sub(1)),
+            (x, y + 1),
+        ];
+        for (nx, ny) in neighbours {
+            let open = map.get(ny).and_then(|row| row.get(nx)) == Some(&0);
+            if open && !reachable[ny][nx] {
+                reachable[ny][nx] = true;
+                queue.push_back((nx, ny));
+            }
+        }
+    }
+
+    reachable
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assets::Vfs;
+    use crate::game::wall_textures::WallTextures;
+
+    #[test]
+    fn test_lint_reports_broken_map() {
+        let clean = MapFile::load("assets/maps/test.toml").unwrap();
+        let wall_textures = WallTextures::load_from(&Vfs::with_base_data())
+            .unwrap()
+            .len();
+        assert!(lint(&clean, wall_textures).is_empty());
+
+        let broken = MapFile::parse(
+            r#"
+            [map]
+            name = "Broken"
+            width = 5
+            height = 4
+            layout = [
+                [1, 1, 0, 1, 1],
+                [1, 0, 1, 0, 1],
+                [1, 0, 1, 0, 7],
+                [1, 1, 1, 1, 1],
+            ]
+
+            [[enemies]]
+            type = "ranged"
+            position = { x = 1.5, y = 1.5 }
+            patrol_points = [{ x = 3.5, y = 1.5 }]
+
+            [player]
+            spawn = { x = 2.5, y = 2.5 }
+            direction = { x = 1.0, y = 0.0 }
+
+            [metadata]
+            author = "Test"
+            description = "Broken map"
+            version = "1.0"
+            "#,
+        )
+        .unwrap();
+
+        let diagnostics = lint(&broken, 4);
+        let cells: Vec<_> = diagnostics.iter().map(|d| d.cell).collect();
+        assert!(has_errors(&diagnostics));
+        assert!(cells.contains(&Some((2, 0)))); // Border gap
+        assert!(cells.contains(&Some((4, 2)))); // Wall ID without texture
+        assert!(cells.contains(&Some((2, 2)))); // Spawn in a wall
+        assert!(cells.contains(&Some((3, 1)))); // Unreachable patrol point
+    }
+}
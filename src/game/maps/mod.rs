@@ -1,8 +1,20 @@
+use crate::assets::Vfs;
+use crate::engine::lighting::{Fog, Lighting, PointLight};
+use crate::engine::mirrors::Mirror;
+use crate::engine::portals::Portal;
+use crate::engine::sectors::SectorMap;
+use crate::engine::sky::Sun;
+use crate::engine::thin_walls::ThinWall;
 use anyhow::Result;
 use glam::Vec2;
 use serde::Deserialize;
 use std::fs;
 
+pub mod lint;
+pub mod sectors;
+
+use sectors::SectorMapFile;
+
 #[derive(Debug, Clone, Copy, Deserialize)]
 pub struct Vec2Def {
     x: f32,
@@ -21,9 +33,20 @@ pub struct MapDef {
     pub width: usize,
     pub height: usize,
     pub layout: Vec<Vec<i32>>,
+    // Optional height layers, same shape as the layout. Floors default to 0,
+    // ceilings to one unit above the floor, and walls to one unit tall.
+    #[serde(default)]
+    pub floor_heights: Option<Vec<Vec<f32>>>,
+    #[serde(default)]
+    pub ceiling_heights: Option<Vec<Vec<f32>>>,
+    #[serde(default)]
+    pub wall_heights: Option<Vec<Vec<f32>>>,
+    // Optional layer marking empty cells open to the sky with 1
+    #[serde(default)]
+    pub outdoor: Option<Vec<Vec<u8>>>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct EnemyProperties {
     pub health: i32,
     pub damage: i32,
@@ -38,7 +61,11 @@ pub struct EnemyDef {
     pub enemy_type: String,
     pub position: Vec2Def,
     pub patrol_points: Vec<Vec2Def>,
-    pub properties: EnemyProperties,
+    // Falls back to the archetype's stats from the content definitions
+    pub properties: Option<EnemyProperties>,
+    // Enemies with the same squad name share what they know of the player
+    #[serde(default)]
+    pub squad: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -54,18 +81,300 @@ pub struct MetadataDef {
     pub version: String,
 }
 
+fn default_crossfade() -> f32 {
+    2.0
+}
+
+/// Layered music: the combat stem fades in while enemies are fighting the player
+#[derive(Debug, Deserialize)]
+pub struct MusicStemsDef {
+    pub base: String,
+    pub combat: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MusicDef {
+    #[serde(default)]
+    pub playlist: Vec<String>,
+    #[serde(default = "default_crossfade")]
+    pub crossfade: f32,
+    pub stems: Option<MusicStemsDef>,
+}
+
+fn default_ambient() -> f32 {
+    1.0
+}
+
+fn default_fog_mode() -> String {
+    "exponential".to_string()
+}
+
+fn default_fog_end() -> f32 {
+    20.0
+}
+
+fn default_fog_density() -> f32 {
+    0.08
+}
+
+fn default_side_shade() -> f32 {
+    0.7
+}
+
+/// Lighting model for the whole map. `fog` is "linear" (uses fog_start and
+/// fog_end) or "exponential" (uses fog_density).
+#[derive(Debug, Deserialize)]
+pub struct LightingDef {
+    #[serde(default = "default_ambient")]
+    pub ambient: f32,
+    #[serde(default)]
+    pub fog_color: [u8; 3],
+    #[serde(default = "default_fog_mode")]
+    pub fog: String,
+    #[serde(default)]
+    pub fog_start: f32,
+    #[serde(default = "default_fog_end")]
+    pub fog_end: f32,
+    #[serde(default = "default_fog_density")]
+    pub fog_density: f32,
+    #[serde(default = "default_side_shade")]
+    pub side_shade: f32,
+}
+
+impl LightingDef {
+    pub fn to_lighting(&self) -> Result<Lighting> {
+        let fog = match self.fog.as_str() {
+            "linear" => Fog::Linear {
+                start: self.fog_start,
+                end: self.fog_end,
+            },
+            "exponential" => Fog::Exponential {
+                density: self.fog_density,
+            },
+            other => anyhow::bail!("Unknown fog mode '{}'", other),
+        };
+
+        Ok(Lighting {
+            ambient: self.ambient,
+            fog_color: self.fog_color,
+            fog,
+            side_shade: self.side_shade,
+        })
+    }
+}
+
+fn default_light_radius() -> f32 {
+    4.0
+}
+
+fn default_light_intensity() -> f32 {
+    0.6
+}
+
+/// A static light baked into the map's light map
+#[derive(Debug, Deserialize)]
+pub struct LightDef {
+    pub position: Vec2Def,
+    #[serde(default = "default_light_radius")]
+    pub radius: f32,
+    #[serde(default = "default_light_intensity")]
+    pub intensity: f32,
+}
+
+impl From<&LightDef> for PointLight {
+    fn from(light: &LightDef) -> Self {
+        PointLight::new(light.position.into(), light.radius, light.intensity)
+    }
+}
+
+fn default_sun_color() -> [u8; 3] {
+    [255, 230, 180]
+}
+
+fn default_sun_strength() -> f32 {
+    0.5
+}
+
+/// Panoramic sky shown above outdoor cells. `sun_angle` is in degrees,
+/// 0 along +x and 90 along +y; leave it out for no sun tint.
+#[derive(Debug, Deserialize)]
+pub struct SkyDef {
+    pub texture: String,
+    pub sun_angle: Option<f32>,
+    #[serde(default = "default_sun_color")]
+    pub sun_color: [u8; 3],
+    #[serde(default = "default_sun_strength")]
+    pub sun_strength: f32,
+}
+
+impl SkyDef {
+    pub fn sun(&self) -> Option<Sun> {
+        self.sun_angle.map(|angle| Sun {
+            angle: angle.to_radians(),
+            color: self.sun_color,
+            strength: self.sun_strength,
+        })
+    }
+}
+
+fn default_mirror_tint() -> [u8; 3] {
+    [190, 210, 230]
+}
+
+fn default_mirror_strength() -> f32 {
+    0.25
+}
+
+/// Makes every wall with ID `wall` reflective. What shows in it is blended towards
+/// `tint` by `strength`; past the bounce limit it draws with its texture instead.
+#[derive(Debug, Deserialize)]
+pub struct MirrorDef {
+    pub wall: i32,
+    #[serde(default = "default_mirror_tint")]
+    pub tint: [u8; 3],
+    #[serde(default = "default_mirror_strength")]
+    pub strength: f32,
+}
+
+impl MirrorDef {
+    pub fn to_mirror(&self) -> Mirror {
+        Mirror {
+            tint: self.tint,
+            strength: self.strength,
+        }
+    }
+}
+
+fn default_portal_kind() -> String {
+    "teleporter".to_string()
+}
+
+/// Links an empty cell to another. `kind` is "teleporter" (players, enemies and
+/// projectiles that step in come out at `target`) or "window" (the same, and rays
+/// show `target` through it). `turns` is how many quarter turns clockwise on the
+/// map the facing turns by.
+#[derive(Debug, Deserialize)]
+pub struct PortalDef {
+    pub cell: [i32; 2],
+    pub target: [i32; 2],
+    #[serde(default = "default_portal_kind")]
+    pub kind: String,
+    #[serde(default)]
+    pub turns: u8,
+}
+
+impl PortalDef {
+    pub fn to_portal(&self) -> Result<Portal> {
+        let window = match self.kind.as_str() {
+            "teleporter" => false,
+            "window" => true,
+            other => anyhow::bail!("Unknown portal kind '{}'", other),
+        };
+        if self.turns > 3 {
+            anyhow::bail!("Portal turns must be 0 to 3, got {}", self.turns);
+        }
+        Ok(Portal {
+            from: (self.cell[0], self.cell[1]),
+            to: (self.target[0], self.target[1]),
+            turns: self.turns,
+            window,
+        })
+    }
+}
+
+fn default_thin_wall_offset() -> f32 {
+    0.5
+}
+
+fn default_solid() -> bool {
+    true
+}
+
+/// A wall segment inside an empty cell. `kind` is "horizontal" or "vertical"
+/// (placed `offset` across the cell), "diagonal" (top-left to bottom-right) or
+/// "anti_diagonal" (bottom-left to top-right).
+#[derive(Debug, Deserialize)]
+pub struct ThinWallDef {
+    pub cell: [i32; 2],
+    pub texture: i32,
+    pub kind: String,
+    #[serde(default = "default_thin_wall_offset")]
+    pub offset: f32,
+    #[serde(default = "default_solid")]
+    pub solid: bool,
+}
+
+impl ThinWallDef {
+    pub fn to_thin_wall(&self) -> Result<ThinWall> {
+        if !(0.0..=1.0).contains(&self.offset) {
+            anyhow::bail!("Thin wall offset {} is outside 0..1", self.offset);
+        }
+        if self.texture <= 0 {
+            anyhow::bail!("Thin wall texture must be a wall ID above 0");
+        }
+
+        let (x, y) = (self.cell[0] as f32, self.cell[1] as f32);
+        let (start, end) = match self.kind.as_str() {
+            "horizontal" => ((0.0, self.offset), (1.0, self.offset)),
+            "vertical" => ((self.offset, 0.0), (self.offset, 1.0)),
+            "diagonal" => ((0.0, 0.0), (1.0, 1.0)),
+            "anti_diagonal" => ((0.0, 1.0), (1.0, 0.0)),
+            other => anyhow::bail!("Unknown thin wall kind '{}'", other),
+        };
+
+        let mut wall = ThinWall::new(
+            Vec2::new(x + start.0, y + start.1),
+            Vec2::new(x + end.0, y + end.1),
+            self.texture,
+        );
+        wall.solid = self.solid;
+        Ok(wall)
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct MapFile {
     pub map: MapDef,
     pub enemies: Vec<EnemyDef>,
     pub player: PlayerDef,
     pub metadata: MetadataDef,
+    pub music: Option<MusicDef>,
+    pub lighting: Option<LightingDef>,
+    #[serde(default)]
+    pub lights: Vec<LightDef>,
+    #[serde(default)]
+    pub thin_walls: Vec<ThinWallDef>,
+    pub sky: Option<SkyDef>,
+    #[serde(default)]
+    pub mirrors: Vec<MirrorDef>,
+    #[serde(default)]
+    pub portals: Vec<PortalDef>,
+    // Set when loaded from a sector map; the grid above is then its stand-in for gameplay
+    #[serde(skip)]
+    pub sectors: Option<SectorMap>,
 }
 
 impl MapFile {
     pub fn load(path: &str) -> Result<Self> {
         let content = fs::read_to_string(path)?;
-        let map_file: MapFile = toml::from_str(&content)?;
+        Self::parse(&content)
+    }
+
+    /// Load a map through the virtual filesystem
+    pub fn load_from(vfs: &Vfs, path: &str) -> Result<Self> {
+        let content = vfs.read_to_string(path).map_err(anyhow::Error::msg)?;
+        Self::parse(&content)
+    }
+
+    /// Parse a grid map, or a sector map into its grid stand-in
+    pub fn parse(content: &str) -> Result<Self> {
+        if sectors::is_sector_map(content) {
+            let sector_file = SectorMapFile::parse(content)?;
+            let sectors = sector_file.to_sector_map()?;
+            return Ok(sector_file.into_map_file(sectors));
+        }
+
+        let map_file: MapFile = toml::from_str(content)?;
 
         // Validate map dimensions
         if map_file.map.layout.len() != map_file.map.height {
@@ -77,6 +386,46 @@ impl MapFile {
             }
         }
 
+        let layers = [
+            ("floor_heights", &map_file.map.floor_heights),
+            ("ceiling_heights", &map_file.map.ceiling_heights),
+            ("wall_heights", &map_file.map.wall_heights),
+        ];
+        for (name, layer) in layers {
+            let Some(rows) = layer else { continue };
+            if rows.len() != map_file.map.height
+                || rows.iter().any(|row| row.len() != map_file.map.width)
+            {
+                anyhow::bail!("Map {} size does not match the layout", name);
+            }
+        }
+
+        if let Some(rows) = &map_file.map.outdoor {
+            if rows.len() != map_file.map.height
+                || rows.iter().any(|row| row.len() != map_file.map.width)
+            {
+                anyhow::bail!("Map outdoor size does not match the layout");
+            }
+        }
+
+        for thin_wall in &map_file.thin_walls {
+            thin_wall.to_thin_wall()?;
+        }
+
+        for portal in &map_file.portals {
+            portal.to_portal()?;
+        }
+
+        for mirror in &map_file.mirrors {
+            if mirror.wall <= 0 {
+                anyhow::bail!("Mirror wall must be a wall ID above 0");
+            }
+        }
+
+        if let Some(lighting) = &map_file.lighting {
+            lighting.to_lighting()?;
+        }
+
         Ok(map_file)
     }
 }
@@ -94,4 +443,31 @@ mod tests {
         assert_eq!(map.enemies[0].enemy_type, "ranged");
         assert_eq!(map.enemies[0].patrol_points.len(), 4);
     }
+
+    #[test]
+    fn test_load_map_music() {
+        let map = MapFile::load("assets/maps/test.toml").unwrap();
+        let music = map.music.unwrap();
+        assert_eq!(music.playlist.len(), 1);
+        assert!(music.stems.is_none());
+    }
+
+    #[test]
+    fn test_load_map_lighting() {
+        let map = MapFile::load("assets/maps/test.toml").unwrap();
+        let lighting = map.lighting.unwrap().to_lighting().unwrap();
+        assert_eq!(
+            lighting.fog,
+            Fog::Linear {
+                start: 3.0,
+                end: 14.0
+            }
+        );
+    }
+
+    #[test]
+    fn test_load_map_lights() {
+        let map = MapFile::load("assets/maps/test.toml").unwrap();
+        assert_eq!(map.lights.len(), 2);
+    }
 }
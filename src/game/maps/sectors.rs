@@ -0,0 +1,205 @@
+use super::{
+    EnemyDef, LightDef, LightingDef, MapDef, MapFile, MetadataDef, MusicDef, PlayerDef, SkyDef,
+    Vec2Def,
+};
+use crate::engine::sectors::{Plane, SectorMap};
+use anyhow::Result;
+use glam::Vec2;
+use serde::Deserialize;
+
+fn default_ceiling() -> f32 {
+    1.0
+}
+
+fn default_wall_texture() -> i32 {
+    1
+}
+
+/// One polygon room. `vertices` index into the map's vertex list; an edge shared
+/// with another sector becomes a portal. Slopes are height change per unit in x and y,
+/// measured from the sector's first vertex.
+#[derive(Debug, Deserialize)]
+pub struct SectorDef {
+    pub vertices: Vec<usize>,
+    #[serde(default)]
+    pub floor: f32,
+    #[serde(default = "default_ceiling")]
+    pub ceiling: f32,
+    #[serde(default)]
+    pub floor_slope: [f32; 2],
+    #[serde(default)]
+    pub ceiling_slope: [f32; 2],
+    #[serde(default = "default_wall_texture")]
+    pub texture: i32,
+    // Per-edge wall IDs, overriding `texture`; edge i runs from vertex i to vertex i + 1
+    pub wall_textures: Option<Vec<i32>>,
+    // Open to the sky; `ceiling` then only sets how tall the walls are
+    #[serde(default)]
+    pub outdoor: bool,
+}
+
+/// A map for the sector renderer. Player, enemies, lights and music work as in grid
+/// maps; vertices are in the same units as grid cells and must not be negative.
+#[derive(Debug, Deserialize)]
+pub struct SectorMapFile {
+    pub name: String,
+    pub vertices: Vec<Vec2Def>,
+    pub sectors: Vec<SectorDef>,
+    pub player: PlayerDef,
+    #[serde(default)]
+    pub enemies: Vec<EnemyDef>,
+    pub metadata: MetadataDef,
+    pub music: Option<MusicDef>,
+    pub lighting: Option<LightingDef>,
+    #[serde(default)]
+    pub lights: Vec<LightDef>,
+    pub sky: Option<SkyDef>,
+}
+
+/// Sector maps are told apart from grid maps by their `[[sectors]]` tables
+pub fn is_sector_map(content: &str) -> bool {
+    content
+        .parse::<toml::Table>()
+        .is_ok_and(|table| table.contains_key("sectors"))
+}
+
+impl SectorMapFile {
+    pub fn parse(content: &str) -> Result<Self> {
+        let map_file: SectorMapFile = toml::from_str(content)?;
+        if map_file
+            .vertices
+            .iter()
+            .any(|&v| Vec2::from(v).min_element() < 0.0)
+        {
+            anyhow::bail!("Sector map vertices must not be negative");
+        }
+        for (i, sector) in map_file.sectors.iter().enumerate() {
+            if let Some(textures) = &sector.wall_textures {
+                if textures.len() != sector.vertices.len() {
+                    anyhow::bail!("Sector {} needs one wall texture per edge", i);
+                }
+            }
+        }
+        map_file.to_sector_map()?;
+        Ok(map_file)
+    }
+
+    pub fn to_sector_map(&self) -> Result<SectorMap> {
+        let vertices: Vec<Vec2> = self.vertices.iter().map(|&v| v.into()).collect();
+        let polygons: Vec<Vec<usize>> = self.sectors.iter().map(|s| s.vertices.clone()).collect();
+
+        let mut planes = Vec::with_capacity(self.sectors.len());
+        let mut textures = Vec::with_capacity(self.sectors.len());
+        for sector in &self.sectors {
+            let anchor = sector
+                .vertices
+                .first()
+                .and_then(|&v| vertices.get(v))
+                .copied()
+                .unwrap_or(Vec2::ZERO);
+            let plane = |height: f32, [x, y]: [f32; 2]| Plane {
+                height,
+                slope: Vec2::new(x, y),
+                anchor,
+            };
+            planes.push((
+                plane(sector.floor, sector.floor_slope),
+                plane(sector.ceiling, sector.ceiling_slope),
+            ));
+            textures.push(
+                sector
+                    .wall_textures
+                    .clone()
+                    .unwrap_or_else(|| vec![sector.texture; sector.vertices.len()]),
+            );
+        }
+
+        let mut map =
+            SectorMap::new(&vertices, &polygons, &planes, &textures).map_err(anyhow::Error::msg)?;
+        for (sector, def) in map.sectors.iter_mut().zip(&self.sectors) {
+            sector.outdoor = def.outdoor;
+        }
+        Ok(map)
+    }
+
+    /// A grid stand-in for the systems that only understand cells: AI, particles,
+    /// audio occlusion, the light map and lint. Cells whose center is outside every
+    /// sector become walls, and heights are sampled at cell centers.
+    pub fn into_map_file(self, sectors: SectorMap) -> MapFile {
+        let max = self
+            .vertices
+            .iter()
+            .fold(Vec2::ZERO, |max, &v| max.max(Vec2::from(v)));
+        // Leave a border of solid cells around the geometry
+        let width = max.x.ceil() as usize + 2;
+        let height = max.y.ceil() as usize + 2;
+
+        let mut layout = vec![vec![1; width]; height];
+        let mut floor_heights = vec![vec![0.0; width]; height];
+        let mut ceiling_heights = vec![vec![1.0; width]; height];
+        let mut outdoor = vec![vec![0; width]; height];
+        for y in 1..height - 1 {
+            for x in 1..width - 1 {
+                let center = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+                if let Some(index) = sectors.sector_at(center) {
+                    let sector = &sectors.sectors[index];
+                    layout[y][x] = 0;
+                    floor_heights[y][x] = sector.floor.at(center);
+                    ceiling_heights[y][x] = sector.ceiling.at(center);
+                    outdoor[y][x] = sector.outdoor as u8;
+                }
+            }
+        }
+
+        MapFile {
+            map: MapDef {
+                name: self.name,
+                width,
+                height,
+                layout,
+                floor_heights: Some(floor_heights),
+                ceiling_heights: Some(ceiling_heights),
+                wall_heights: None,
+                outdoor: Some(outdoor),
+            },
+            enemies: self.enemies,
+            player: self.player,
+            metadata: self.metadata,
+            music: self.music,
+            lighting: self.lighting,
+            lights: self.lights,
+            thin_walls: Vec::new(),
+            sky: self.sky,
+            mirrors: Vec::new(),
+            portals: Vec::new(),
+            sectors: Some(sectors),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_load_sector_map() {
+        let content = fs::read_to_string("assets/maps/sectors.toml").unwrap();
+        assert!(is_sector_map(&content));
+        assert!(!is_sector_map(
+            &fs::read_to_string("assets/maps/test.toml").unwrap()
+        ));
+
+        let map_file = SectorMapFile::parse(&content).unwrap();
+        let sectors = map_file.to_sector_map().unwrap();
+        assert_eq!(sectors.sectors.len(), map_file.sectors.len());
+
+        let spawn: Vec2 = map_file.player.spawn.into();
+        assert!(sectors.sector_at(spawn).is_some());
+
+        let grid = map_file.into_map_file(sectors);
+        let (x, y) = (spawn.x as usize, spawn.y as usize);
+        assert_eq!(grid.map.layout[y][x], 0);
+        assert_eq!(grid.map.layout[0][0], 1);
+    }
+}
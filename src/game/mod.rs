@@ -1,9 +1,14 @@
-use crate::engine::texture::Texture;
+use crate::engine::lighting::{DynamicLight, PointLight};
+use crate::engine::portals::Portals;
+use crate::engine::thin_walls::ThinWalls;
 
 pub mod ai;
+pub mod content;
 pub mod maps;
+pub mod mod_menu;
 pub mod particle;
 pub mod state;
+pub mod wall_textures;
 pub mod weapon;
 pub mod world;
 
@@ -11,12 +16,18 @@ pub use ai::enemy::Enemy;
 pub use particle::{Particle, ParticleSystem};
 pub use weapon::Weapon;
 
+// Projectiles glow, and flash briefly where they hit a wall
+const PROJECTILE_LIGHT: (f32, f32) = (1.5, 0.5); // (radius, intensity)
+const IMPACT_LIGHT: (f32, f32, f32) = (2.5, 0.9, 0.15); // (radius, intensity, seconds)
+
 pub struct Game {
     pub width: u32,
     pub height: u32,
     pub weapon: Option<Weapon>,
     pub particles: ParticleSystem,
     pub player_health: i32,
+    pub lights: Vec<DynamicLight>,
+    pub time: f32, // Seconds of play, for animated wall textures
 }
 
 impl Game {
@@ -27,19 +38,46 @@ impl Game {
             weapon: None,
             particles: ParticleSystem::new(),
             player_health: 100,
+            lights: Vec::new(),
+            time: 0.0,
         }
     }
 
-    pub fn load_weapon(&mut self, idle_path: &str, fire_path: &str) -> Result<(), String> {
-        let idle_texture = Texture::load(idle_path)?;
-        let fire_texture = Texture::load(fire_path)?;
-        self.weapon = Some(Weapon::new(idle_texture, fire_texture));
-        Ok(())
-    }
+    pub fn update(&mut self, dt: f32, map: &[Vec<i32>], walls: &ThinWalls, portals: &Portals) {
+        self.time += dt;
 
-    pub fn update(&mut self, dt: f32, map: &[Vec<i32>]) {
         // Update particles
-        self.particles.update(dt, map);
+        self.particles.update(dt, map, walls, portals);
+
+        // Fade out short-lived lights and light up projectile impacts
+        for light in &mut self.lights {
+            light.update(dt);
+        }
+        self.lights.retain(|light| light.is_alive());
+
+        let (radius, intensity, duration) = IMPACT_LIGHT;
+        for position in self.particles.take_impacts() {
+            self.add_light(PointLight::new(position, radius, intensity), duration);
+        }
+    }
+
+    pub fn add_light(&mut self, light: PointLight, duration: f32) {
+        self.lights.push(DynamicLight::new(light, duration));
+    }
+
+    /// Every dynamic light for this frame, including the glow of live projectiles
+    pub fn frame_lights(&self) -> Vec<PointLight> {
+        let (radius, intensity) = PROJECTILE_LIGHT;
+        self.lights
+            .iter()
+            .map(|light| light.current())
+            .chain(
+                self.particles
+                    .get_particles()
+                    .iter()
+                    .map(|particle| PointLight::new(particle.position, radius, intensity)),
+            )
+            .collect()
     }
 
     pub fn render(&mut self, frame: &mut [u8]) {
@@ -0,0 +1,122 @@
+use crate::engine::font::{self, GLYPH_HEIGHT};
+use crate::mods::{ModList, ModStatus};
+use log::{error, info};
+
+const SCALE: u32 = 2;
+const PADDING: u32 = 16;
+const LINE_HEIGHT: u32 = (GLYPH_HEIGHT + 4) * SCALE;
+
+const TITLE_COLOR: [u8; 3] = [0xFF, 0xFF, 0xFF];
+const SELECTED_COLOR: [u8; 3] = [0xFF, 0xD0, 0x40];
+const ENABLED_COLOR: [u8; 3] = [0xA0, 0xE0, 0xA0];
+const DISABLED_COLOR: [u8; 3] = [0x90, 0x90, 0x90];
+const ERROR_COLOR: [u8; 3] = [0xE0, 0x60, 0x60];
+
+/// In-game list of installed mods. Changes are saved right away
+/// and take effect the next time the game starts.
+pub struct ModMenu {
+    pub visible: bool,
+    selected: usize,
+    changed: bool,
+}
+
+impl ModMenu {
+    pub fn new() -> Self {
+        Self {
+            visible: false,
+            selected: 0,
+            changed: false,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self, mods: &ModList) {
+        if self.selected + 1 < mods.mods.len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn toggle_selected(&mut self, mods: &mut ModList) {
+        let Some(entry) = mods.mods.get(self.selected) else {
+            return;
+        };
+        let enabled = !entry.enabled;
+        let id = entry.id.clone();
+
+        match mods.set_enabled(self.selected, enabled) {
+            Ok(()) => {
+                info!(
+                    "Mod {} {} (restart to apply)",
+                    id,
+                    if enabled { "enabled" } else { "disabled" }
+                );
+                self.changed = true;
+            }
+            Err(e) => error!("Failed to save mod settings: {}", e),
+        }
+    }
+
+    pub fn render(&self, frame: &mut [u8], width: u32, mods: &ModList) {
+        // Darken the scene behind the list
+        for pixel in frame.chunks_exact_mut(4) {
+            pixel[0] /= 3;
+            pixel[1] /= 3;
+            pixel[2] /= 3;
+        }
+
+        let mut y = PADDING;
+        let mut line = |frame: &mut [u8], text: &str, color: [u8; 3]| {
+            font::draw_text(frame, width, PADDING, y, text, color, SCALE);
+            y += LINE_HEIGHT;
+        };
+
+        line(frame, "MODS  (UP/DOWN, ENTER TO TOGGLE)", TITLE_COLOR);
+        if mods.mods.is_empty() {
+            line(frame, "NO MODS INSTALLED", DISABLED_COLOR);
+        }
+
+        for (i, entry) in mods.mods.iter().enumerate() {
+            let marker = if i == self.selected { ">" } else { " " };
+            let check = if entry.enabled { "X" } else { " " };
+            let mut text = format!("{} [{}] {} {}", marker, check, entry.name, entry.version);
+            let color = match &entry.status {
+                ModStatus::MissingDependency(dependency) => {
+                    text.push_str(&format!(" - NEEDS {}", dependency));
+                    ERROR_COLOR
+                }
+                ModStatus::Invalid(_) => {
+                    text.push_str(" - INVALID MANIFEST");
+                    ERROR_COLOR
+                }
+                _ if i == self.selected => SELECTED_COLOR,
+                _ if entry.enabled => ENABLED_COLOR,
+                _ => DISABLED_COLOR,
+            };
+            line(frame, &text, color);
+        }
+
+        if !mods.conflicts.is_empty() {
+            line(
+                frame,
+                &format!("{} CONFLICTS, SEE LOG", mods.conflicts.len()),
+                ERROR_COLOR,
+            );
+        }
+        if self.changed {
+            line(frame, "RESTART TO APPLY CHANGES", SELECTED_COLOR);
+        }
+    }
+}
+
+impl Default for ModMenu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,9 +1,12 @@
+use crate::engine::portals::Portals;
 use crate::engine::texture::Texture;
+use crate::engine::thin_walls::ThinWalls;
 use glam::Vec2;
 
 pub struct Particle {
     pub position: Vec2,
     pub velocity: Vec2,
+    pub z: f32, // Height it flies at
     pub lifetime: f32,
     pub texture: Texture,
     pub damage: i32,
@@ -21,6 +24,7 @@ impl Particle {
         Self {
             position,
             velocity,
+            z: 0.5,
             lifetime: 2.0, // 2 seconds lifetime
             texture,
             damage,
@@ -28,6 +32,11 @@ impl Particle {
         }
     }
 
+    pub fn with_z(mut self, z: f32) -> Self {
+        self.z = z;
+        self
+    }
+
     pub fn update(&mut self, dt: f32) {
         self.position += self.velocity * dt;
         self.lifetime -= dt;
@@ -40,12 +49,14 @@ impl Particle {
 
 pub struct ParticleSystem {
     particles: Vec<Particle>,
+    impacts: Vec<Vec2>, // Where particles hit walls since the last take_impacts
 }
 
 impl ParticleSystem {
     pub fn new() -> Self {
         Self {
             particles: Vec::new(),
+            impacts: Vec::new(),
         }
     }
 
@@ -53,15 +64,30 @@ impl ParticleSystem {
         self.particles.push(particle);
     }
 
-    pub fn update(&mut self, dt: f32, map: &[Vec<i32>]) {
+    pub fn update(&mut self, dt: f32, map: &[Vec<i32>], walls: &ThinWalls, portals: &Portals) {
+        let impacts = &mut self.impacts;
         self.particles.retain_mut(|particle| {
+            let previous = particle.position;
             particle.update(dt);
 
+            // Solid thin walls stop particles where they cross them
+            if let Some(hit) = walls.crossing(previous, particle.position) {
+                impacts.push(hit);
+                return false;
+            }
+
+            // Projectiles fly on out of portals
+            if let Some(fold) = portals.travel(particle.position) {
+                particle.position = fold.point(particle.position);
+                particle.velocity = fold.direction(particle.velocity);
+            }
+
             // Check collision with walls
             let map_x = particle.position.x.floor() as usize;
             let map_y = particle.position.y.floor() as usize;
 
             if map_x >= map[0].len() || map_y >= map.len() || map[map_y][map_x] != 0 {
+                impacts.push(particle.position - particle.velocity * dt);
                 return false; // Remove particle if it hits a wall
             }
 
@@ -73,6 +99,10 @@ impl ParticleSystem {
         &self.particles
     }
 
+    pub fn take_impacts(&mut self) -> Vec<Vec2> {
+        std::mem::take(&mut self.impacts)
+    }
+
     pub fn clear(&mut self) {
         self.particles.clear();
     }
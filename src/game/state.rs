@@ -39,7 +39,7 @@ impl GameState {
 
         // Render world, enemies, and particles
         self.raycaster
-            .render(&self.camera, enemies, particles, frame);
+            .render(&self.camera, enemies, particles, &[], frame);
     }
 
     pub fn toggle_pause(&mut self) {
@@ -0,0 +1,119 @@
+use crate::assets::Vfs;
+use crate::engine::texture::Animation;
+use anyhow::Result;
+use glam::Vec2;
+use serde::Deserialize;
+
+pub const WALL_TEXTURES_PATH: &str = "assets/textures/walls.toml";
+
+/// The texture of one wall ID. `texture` is a still image; `frames` cycles through
+/// images `fps` times a second instead. `scroll` slides either along u and v, in
+/// texture lengths per second, for flowing water or scrolling screens.
+#[derive(Debug, Clone, Deserialize)]
+pub struct WallTextureDef {
+    pub texture: Option<String>,
+    #[serde(default)]
+    pub frames: Vec<String>,
+    #[serde(default)]
+    pub fps: f32,
+    #[serde(default)]
+    pub scroll: [f32; 2],
+}
+
+impl WallTextureDef {
+    /// Image paths in the order they show
+    pub fn frames(&self) -> Vec<&str> {
+        self.texture
+            .iter()
+            .chain(&self.frames)
+            .map(String::as_str)
+            .collect()
+    }
+
+    pub fn animation(&self) -> Animation {
+        Animation {
+            fps: self.fps,
+            scroll: Vec2::from(self.scroll),
+        }
+    }
+}
+
+/// Wall textures from `assets/textures/walls.toml`; the first entry is wall ID 1
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct WallTextures {
+    #[serde(default)]
+    pub walls: Vec<WallTextureDef>,
+}
+
+impl WallTextures {
+    /// Load the definitions through the virtual filesystem, so mods can replace them
+    pub fn load_from(vfs: &Vfs) -> Result<Self> {
+        let content = vfs
+            .read_to_string(WALL_TEXTURES_PATH)
+            .map_err(anyhow::Error::msg)?;
+        Self::parse(&content)
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        let textures: WallTextures = toml::from_str(content)?;
+
+        for (index, wall) in textures.walls.iter().enumerate() {
+            let id = index + 1;
+            match (&wall.texture, wall.frames.len()) {
+                (Some(_), 0) | (None, 1..) => {}
+                (Some(_), _) => anyhow::bail!("Wall {} has both a texture and frames", id),
+                (None, 0) => anyhow::bail!("Wall {} needs a texture or frames", id),
+            }
+            if wall.fps < 0.0 {
+                anyhow::bail!("Wall {} has a negative fps", id);
+            }
+            if wall.frames.len() > 1 && wall.fps == 0.0 {
+                anyhow::bail!("Wall {} has several frames but no fps", id);
+            }
+        }
+
+        Ok(textures)
+    }
+
+    pub fn len(&self) -> usize {
+        self.walls.len()
+    }
+
+    /// Every image the walls use, for asset validation
+    pub fn asset_paths(&self) -> Vec<&str> {
+        self.walls.iter().flat_map(WallTextureDef::frames).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_wall_textures() {
+        let textures = WallTextures::load_from(&Vfs::with_base_data()).unwrap();
+        assert!(!textures.walls.is_empty());
+        let still = Animation {
+            fps: 0.0,
+            scroll: Vec2::ZERO,
+        };
+        assert_eq!(textures.walls[0].animation(), still);
+
+        let animated = WallTextures::parse(
+            r#"
+            [[walls]]
+            frames = ["a.png", "b.png"]
+            fps = 8.0
+            scroll = [0.0, 0.25]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(animated.asset_paths(), vec!["a.png", "b.png"]);
+        assert_eq!(animated.walls[0].animation().scroll, Vec2::new(0.0, 0.25));
+
+        let still_frames = "[[walls]]\nframes = [\"a.png\", \"b.png\"]";
+        assert!(WallTextures::parse(still_frames).is_err());
+        let both = "[[walls]]\ntexture = \"a.png\"\nframes = [\"b.png\"]";
+        assert!(WallTextures::parse(both).is_err());
+    }
+}
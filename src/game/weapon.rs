@@ -1,29 +1,49 @@
-use crate::engine::texture::Texture;
+use crate::engine::sprite::{Animator, Sprite};
+use crate::game::content::{FIRE_CLIP, IDLE_CLIP, SHOOT_EVENT};
 use glam::Vec2;
+use std::rc::Rc;
 use std::time::Instant;
 
 pub struct Weapon {
-    idle_texture: Texture,
-    fire_texture: Texture,
-    firing: bool,
+    animator: Animator,
     last_shot: Instant,
     bob_offset: f32,
     bob_time: f32,
+    damage: i32,
+    cooldown: f32,
+    projectile_speed: f32,
 }
 
 impl Weapon {
-    pub fn new(idle_texture: Texture, fire_texture: Texture) -> Self {
+    pub fn new(sprite: Rc<Sprite>) -> Self {
         Self {
-            idle_texture,
-            fire_texture,
-            firing: false,
+            animator: Animator::new(sprite, IDLE_CLIP),
             last_shot: Instant::now(),
             bob_offset: 0.0,
             bob_time: 0.0,
+            damage: 20,
+            cooldown: 0.5,
+            projectile_speed: 10.0,
         }
     }
 
-    pub fn update(&mut self, dt: f32, moving: bool) {
+    pub fn with_stats(mut self, damage: i32, cooldown: f32, projectile_speed: f32) -> Self {
+        self.damage = damage;
+        self.cooldown = cooldown;
+        self.projectile_speed = projectile_speed;
+        self
+    }
+
+    pub fn damage(&self) -> i32 {
+        self.damage
+    }
+
+    pub fn projectile_speed(&self) -> f32 {
+        self.projectile_speed
+    }
+
+    /// Returns true when the shot leaves, on the fire clip's shoot frame
+    pub fn update(&mut self, dt: f32, moving: bool) -> bool {
         // Update weapon bob when moving
         if moving {
             self.bob_time += dt * 5.0;
@@ -33,18 +53,14 @@ impl Weapon {
             self.bob_offset = 0.0;
         }
 
-        // Reset firing state if enough time has passed
-        if self.firing && self.last_shot.elapsed().as_secs_f32() > 0.1 {
-            self.firing = false;
-        }
+        self.animator
+            .update(dt)
+            .iter()
+            .any(|event| event == SHOOT_EVENT)
     }
 
     pub fn render(&self, frame: &mut [u8], width: u32, height: u32) {
-        let texture = if self.firing {
-            &self.fire_texture
-        } else {
-            &self.idle_texture
-        };
+        let texture = self.animator.texture();
 
         // Compute weapon position (centered horizontally, bottom of screen)
         let weapon_width = width / 4; // Make weapon 1/4 of screen width
@@ -106,10 +122,11 @@ impl Weapon {
         }
     }
 
+    /// Pull the trigger, starting the fire clip if the weapon is ready
     pub fn fire(&mut self) -> bool {
         let now = Instant::now();
-        if now.duration_since(self.last_shot).as_secs_f32() >= 0.5 {
-            self.firing = true;
+        if now.duration_since(self.last_shot).as_secs_f32() >= self.cooldown {
+            self.animator.restart(FIRE_CLIP);
             self.last_shot = now;
             true
         } else {
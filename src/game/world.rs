@@ -1,11 +1,26 @@
-use crate::game::maps::MapFile;
+use crate::engine::heights::HeightMap;
+use crate::engine::portals::{Portal, Portals};
+use crate::engine::thin_walls::{ThinWall, ThinWalls};
+use crate::game::maps::{EnemyProperties, MapFile};
 use anyhow::Result;
 use glam::Vec2;
 
+/// Where and how to create one enemy from a map
+pub struct EnemySpawn {
+    pub enemy_type: String,
+    pub position: Vec2,
+    pub patrol_points: Vec<Vec2>,
+    pub properties: Option<EnemyProperties>,
+    pub squad: Option<String>,
+}
+
 pub struct World {
     pub spawn_point: Vec2,
     pub spawn_direction: Vec2,
     pub map: Vec<Vec<i32>>,
+    pub heights: HeightMap,
+    pub thin_walls: ThinWalls,
+    pub portals: Portals,
     pub width: usize,
     pub height: usize,
     pub name: String,
@@ -30,6 +45,9 @@ impl World {
 
         Self {
             map,
+            heights: HeightMap::flat(width, height),
+            thin_walls: ThinWalls::empty(),
+            portals: Portals::empty(),
             width,
             height,
             spawn_point: Vec2::new(1.5, 1.5),
@@ -40,9 +58,30 @@ impl World {
         }
     }
 
-    pub fn load_from_map(map_file: &MapFile) -> Result<(Self, Vec<(Vec2, Vec<Vec2>)>)> {
+    pub fn load_from_map(map_file: &MapFile) -> Result<(Self, Vec<EnemySpawn>)> {
+        let thin_walls = map_file
+            .thin_walls
+            .iter()
+            .map(|def| def.to_thin_wall())
+            .collect::<Result<Vec<ThinWall>>>()?;
+        let portals = map_file
+            .portals
+            .iter()
+            .map(|def| def.to_portal())
+            .collect::<Result<Vec<Portal>>>()?;
+
         let mut world = Self {
             map: map_file.map.layout.clone(),
+            heights: HeightMap::from_layers(
+                map_file.map.width,
+                map_file.map.height,
+                map_file.map.floor_heights.as_deref(),
+                map_file.map.ceiling_heights.as_deref(),
+                map_file.map.wall_heights.as_deref(),
+            )
+            .with_sky(map_file.map.outdoor.as_deref()),
+            thin_walls: ThinWalls::new(map_file.map.width, map_file.map.height, thin_walls),
+            portals: Portals::new(map_file.map.width, map_file.map.height, portals),
             width: map_file.map.width,
             height: map_file.map.height,
             spawn_point: map_file.player.spawn.clone().into(),
@@ -53,17 +92,19 @@ impl World {
         };
 
         // Collect enemy spawn points and patrol paths
-        let enemy_data: Vec<(Vec2, Vec<Vec2>)> = map_file
+        let enemy_data: Vec<EnemySpawn> = map_file
             .enemies
             .iter()
-            .map(|enemy| {
-                let pos: Vec2 = enemy.position.clone().into();
-                let patrol: Vec<Vec2> = enemy
+            .map(|enemy| EnemySpawn {
+                enemy_type: enemy.enemy_type.clone(),
+                position: enemy.position.clone().into(),
+                patrol_points: enemy
                     .patrol_points
                     .iter()
                     .map(|p| p.clone().into())
-                    .collect();
-                (pos, patrol)
+                    .collect(),
+                properties: enemy.properties.clone(),
+                squad: enemy.squad.clone(),
             })
             .collect();
 
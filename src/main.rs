@@ -1,14 +1,36 @@
+mod assets;
 mod audio;
+mod cli;
+mod config;
 mod engine;
 mod game;
+mod mods;
 
 use anyhow::Result;
-use audio::sound::{MusicPlayer, SoundManager};
-use engine::{Camera, Raycaster};
-use game::{maps::MapFile, world::World, Enemy, Game, Particle, Weapon};
+use assets::{AssetKind, Vfs};
+use audio::buffer::SoundBuffer;
+use audio::{AudioManager, Bus, SoundEffect, SoundPriority};
+use config::Config;
+use engine::camera::{EYE_HEIGHT, HEAD_ROOM};
+use engine::lighting::{DynamicLight, LightMap, PointLight};
+use engine::sky::Sky;
+use engine::sprite::Sprite;
+use engine::thin_walls::ThinWalls;
+use engine::{Camera, Raycaster, Texture};
+use game::ai::{AIState, EnemyAction, Hurt, Noise, Squads};
+use game::content::{two_frame_sprite, Content, SpriteDef};
+use game::maps::lint::{self, Severity};
+use game::maps::{EnemyProperties, MapFile};
+use game::mod_menu::ModMenu;
+use game::wall_textures::WallTextures;
+use game::{world::World, Enemy, Game, Particle, Weapon};
 use glam::Vec2;
-use log::{error, info};
+use log::{error, info, warn};
+use mods::{ModList, MODS_DIR};
 use pixels::{Pixels, SurfaceTexture};
+use std::collections::HashMap;
+use std::path::Path;
+use std::rc::Rc;
 use std::time::Instant;
 use winit::dpi::LogicalSize;
 use winit::event::{DeviceEvent, ElementState, Event, MouseButton, VirtualKeyCode, WindowEvent};
@@ -21,6 +43,24 @@ const MOVE_SPEED: f32 = 2.5; // Units per second
 const MOUSE_SENSITIVITY: f32 = 0.002; // Slightly reduced for smoother control
 const FOOTSTEP_INTERVAL: f32 = 0.5; // Time between footstep sounds
 
+// How many open cells away enemies can hear the player; crouching steps are silent
+const FOOTSTEP_NOISE: f32 = 4.0;
+const GUNSHOT_NOISE: f32 = 14.0;
+
+const PROJECTILE_DROP: f32 = 0.1; // Shots leave a little below eye level
+const MUZZLE_FLASH: (f32, f32, f32) = (3.0, 0.8, 0.08); // (radius, intensity, seconds)
+
+const PROJECTILE_TEXTURE: &str = "assets/textures/particles/purple.png";
+// (name, path, bus, priority, max overlapping instances)
+// Weapon and enemy sounds come from the content definitions instead.
+const SOUND_EFFECTS: [(&str, &str, Bus, SoundPriority, usize); 1] = [(
+    "step",
+    "assets/audio/effects/step.wav",
+    Bus::Effects,
+    SoundPriority::Low,
+    2,
+)];
+
 struct GameState {
     camera: Camera,
     raycaster: Raycaster,
@@ -32,89 +72,317 @@ struct GameState {
     move_right: bool,
     game_focused: bool,
     world: World,
-    sound_manager: SoundManager,
-    music_player: MusicPlayer,
+    audio: AudioManager,
     game: Game,
     enemies: Vec<Enemy>,
+    projectile_texture: Option<Texture>,
+    weapon_sound: String,
+    mods: ModList,
+    mod_menu: ModMenu,
+    noises: Vec<Noise>, // Made by the player this frame, heard before enemies update
+    squads: Squads,
 }
 
 impl GameState {
-    fn new(stream_handle: rodio::OutputStreamHandle) -> Result<Self> {
-        info!("Loading map from assets/maps/test.toml");
-        let map_file = MapFile::load("assets/maps/test.toml")?;
-        let (world, enemy_spawns) = World::load_from_map(&map_file)?;
+    fn new(vfs: &Vfs, config: &Config, mut audio: AudioManager, mut mods: ModList) -> Result<Self> {
+        // Enemy archetypes and weapons, with enabled mods layered on top
+        let mut content = Content::load(vfs).unwrap_or_else(|e| {
+            error!("Failed to load content definitions: {}", e);
+            Content::default()
+        });
+        mods.apply_content(&mut content);
+        mods.log();
+        let wall_textures = WallTextures::load_from(vfs).unwrap_or_else(|e| {
+            error!("Failed to load wall textures: {}", e);
+            WallTextures::default()
+        });
+
+        // Check every referenced asset up front and report problems once
+        let (content_textures, content_sounds) = content.asset_paths();
+        let map_path = config.gameplay.map.as_str();
+        let mut referenced: Vec<(AssetKind, &str)> = vec![(AssetKind::Map, map_path)];
+        referenced.extend(
+            wall_textures
+                .asset_paths()
+                .into_iter()
+                .map(|path| (AssetKind::Texture, path)),
+        );
+        referenced.push((AssetKind::Texture, PROJECTILE_TEXTURE));
+        referenced.extend(
+            content_textures
+                .iter()
+                .map(|&path| (AssetKind::Texture, path)),
+        );
+        referenced.extend(content_sounds.iter().map(|&path| (AssetKind::Sound, path)));
+        referenced.extend(
+            SOUND_EFFECTS
+                .iter()
+                .map(|&(_, path, ..)| (AssetKind::Sound, path)),
+        );
+
+        info!("Loading map from {}", map_path);
+        let mut map_file = MapFile::load_from(vfs, map_path)?;
+        let sectors = map_file.sectors.take();
+        let diagnostics = lint::lint(&map_file, wall_textures.len());
+        for diagnostic in &diagnostics {
+            match diagnostic.severity {
+                Severity::Error => error!("{}: {}", map_path, diagnostic),
+                Severity::Warning => warn!("{}: {}", map_path, diagnostic),
+            }
+        }
+        if lint::has_errors(&diagnostics) {
+            anyhow::bail!("Map {} failed validation", map_path);
+        }
+        let (mut world, enemy_spawns) = World::load_from_map(&map_file)?;
+        if let Some(sectors) = &sectors {
+            // Angled sector walls collide exactly instead of by grid cell
+            world.thin_walls = ThinWalls::new(world.width, world.height, sectors.solid_walls());
+        }
+
+        let music_paths: Vec<&str> = map_file
+            .music
+            .iter()
+            .flat_map(|music| {
+                let stems = music
+                    .stems
+                    .iter()
+                    .flat_map(|stems| [stems.base.as_str(), stems.combat.as_str()]);
+                music.playlist.iter().map(String::as_str).chain(stems)
+            })
+            .collect();
+        referenced.extend(music_paths.iter().map(|&path| (AssetKind::Music, path)));
+        if let Some(sky) = &map_file.sky {
+            referenced.push((AssetKind::Texture, sky.texture.as_str()));
+        }
+        vfs.validate(&referenced).log();
 
         let mut raycaster = Raycaster::new(WIDTH, HEIGHT);
         raycaster.set_map(world.map.clone());
+        raycaster.set_draw_distance(config.graphics.draw_distance);
+        raycaster.set_texture_quality(
+            config.graphics.texture_quality.base_mip(),
+            config.graphics.texture_filter,
+        );
+        raycaster.set_max_reflections(config.graphics.max_reflections);
+        let mirrors = map_file.mirrors.iter().map(|m| (m.wall, m.to_mirror()));
+        raycaster.set_mirrors(mirrors.collect());
+        if let Some(lighting) = &map_file.lighting {
+            raycaster.set_lighting(lighting.to_lighting()?);
+        }
+        let map_lights: Vec<PointLight> = map_file.lights.iter().map(PointLight::from).collect();
+        raycaster.set_light_map(LightMap::build(&world.map, &map_lights));
+        raycaster.set_heights(world.heights.clone());
+        raycaster.set_thin_walls(world.thin_walls.clone());
+        raycaster.set_portals(world.portals.clone());
+        raycaster.set_sectors(sectors);
+        if let Some(sky) = &map_file.sky {
+            match Texture::load_from(vfs, &sky.texture) {
+                Ok(texture) => raycaster.set_sky(Some(Sky::new(texture).with_sun(sky.sun()))),
+                Err(e) => error!("Failed to load sky {}: {}", sky.texture, e),
+            }
+        }
 
         // Load wall textures
-        let texture_paths = [
-            "assets/textures/walls/brick.png",
-            "assets/textures/walls/greystone.png",
-            "assets/textures/walls/redbrick.png",
-            "assets/textures/walls/stone.png",
-        ];
-
-        for path in texture_paths.iter() {
-            if let Err(e) = raycaster.load_texture(path) {
-                error!("Failed to load texture {}: {}", path, e);
+        for wall in &wall_textures.walls {
+            let frames = wall.frames();
+            if frames.iter().all(|path| vfs.exists(path)) {
+                if let Err(e) = raycaster.load_texture(vfs, &frames, wall.animation()) {
+                    error!("Failed to load texture {}: {}", frames.join(", "), e);
+                }
             }
         }
 
         // Initialize audio
-        let mut sound_manager = SoundManager::new(stream_handle.clone());
-        let mut music_player = MusicPlayer::new(stream_handle);
+        audio.sounds.set_map(world.map.clone());
+        audio.sounds.set_listener(world.spawn_point);
 
         // Register sound effects
-        sound_manager.register_sound("step", "assets/audio/effects/step.wav");
-        sound_manager.register_sound("gun1", "assets/audio/weapons/gun1.wav");
-        sound_manager.register_sound("gun2", "assets/audio/weapons/gun2.wav");
+        let mut register_sound =
+            |name: &str, path: &str, bus: Bus, priority: SoundPriority, max_instances: usize| {
+                if !vfs.exists(path) {
+                    audio.sounds.mark_unavailable(name);
+                    return;
+                }
+                let effect = SoundEffect::new(name, path)
+                    .with_bus(bus)
+                    .with_priority(priority)
+                    .with_max_instances(max_instances);
+                if let Err(e) = audio.sounds.register_effect(vfs, effect) {
+                    error!("Failed to register sound: {}", e);
+                }
+            };
+        for &(name, path, bus, priority, max_instances) in SOUND_EFFECTS.iter() {
+            register_sound(name, path, bus, priority, max_instances);
+        }
+        for (name, weapon) in &content.weapons {
+            if let Some(path) = &weapon.sound {
+                let name = format!("weapon.{}", name);
+                register_sound(&name, path, Bus::Weapons, SoundPriority::High, 3);
+            }
+        }
+        for (name, enemy) in &content.enemies {
+            if let Some(path) = &enemy.attack_sound {
+                let name = format!("enemy.{}", name);
+                register_sound(&name, path, Bus::Weapons, SoundPriority::Normal, 4);
+            }
+            let hooks = [
+                (Hurt::Pain, &enemy.pain_sound),
+                (Hurt::Death, &enemy.death_sound),
+                (Hurt::Gibbed, &enemy.gib_sound),
+            ];
+            for (hurt, sound) in hooks {
+                if let Some(path) = sound {
+                    let name = format!("enemy.{}.{}", name, hurt.hook());
+                    register_sound(&name, path, Bus::Effects, SoundPriority::Normal, 4);
+                }
+            }
+        }
 
-        // Start background music
-        if let Err(e) = music_player.play_music("assets/audio/music/track0.wav") {
-            error!("Failed to play background music: {}", e);
+        // Start background music declared by the map
+        if let Some(music) = &map_file.music {
+            let load_track = |path: &str| -> Option<SoundBuffer> {
+                if !vfs.exists(path) {
+                    return None;
+                }
+                SoundBuffer::load(vfs, path)
+                    .map_err(|e| error!("Failed to load music {}: {}", path, e))
+                    .ok()
+            };
+            let result = match &music.stems {
+                Some(stems) => match (load_track(&stems.base), load_track(&stems.combat)) {
+                    (Some(base), Some(combat)) => {
+                        audio.music.play_stems(base, combat, music.crossfade)
+                    }
+                    _ => Ok(()),
+                },
+                None => {
+                    let playlist: Vec<SoundBuffer> = music
+                        .playlist
+                        .iter()
+                        .filter_map(|path| load_track(path))
+                        .collect();
+                    if playlist.is_empty() {
+                        Ok(())
+                    } else {
+                        audio.music.play_playlist(playlist, music.crossfade)
+                    }
+                }
+            };
+            if let Err(e) = result {
+                error!("Failed to play background music: {}", e);
+            }
         }
 
+        let load_texture = |path: &str| -> Option<Texture> {
+            if !vfs.exists(path) {
+                return None;
+            }
+            Texture::load_from(vfs, path)
+                .map_err(|e| error!("Failed to load texture {}: {}", path, e))
+                .ok()
+        };
+
+        // A sprite sheet with named clips, or else the plain idle and fire textures
+        let load_sprite =
+            |def: Option<&SpriteDef>, idle: &str, fire: Option<&str>| -> Option<Rc<Sprite>> {
+                if let Some(def) = def {
+                    match load_texture(&def.sheet).map(|sheet| def.to_sprite(&sheet)) {
+                        Some(Ok(sprite)) => return Some(Rc::new(sprite)),
+                        Some(Err(e)) => error!("Bad sprite sheet {}: {}", def.sheet, e),
+                        None => {}
+                    }
+                }
+                let idle_texture = load_texture(idle)?;
+                let fire_texture = match fire {
+                    Some(path) => load_texture(path)?,
+                    None => idle_texture.clone(),
+                };
+                Some(Rc::new(two_frame_sprite(idle_texture, fire_texture)))
+            };
+
         // Initialize game state
         let mut game = Game::new(WIDTH, HEIGHT);
 
-        // Load weapon
-        if let Ok(idle_texture) =
-            engine::texture::Texture::load("assets/textures/weapons/gun1/idle.png")
-        {
-            if let Ok(fire_texture) =
-                engine::texture::Texture::load("assets/textures/weapons/gun1/fire.png")
-            {
-                game.weapon = Some(Weapon::new(idle_texture, fire_texture));
+        // Load the player's weapon
+        let weapon_name = content.player_weapon.clone().unwrap_or_default();
+        match content.weapons.get(&weapon_name) {
+            Some(def) => {
+                let sprite = load_sprite(
+                    def.sprite.as_ref(),
+                    &def.idle_texture,
+                    Some(&def.fire_texture),
+                );
+                if let Some(sprite) = sprite {
+                    game.weapon = Some(Weapon::new(sprite).with_stats(
+                        def.damage,
+                        def.cooldown,
+                        def.projectile_speed,
+                    ));
+                }
             }
+            None => error!("Player weapon '{}' is not defined", weapon_name),
         }
 
+        let projectile_texture = load_texture(PROJECTILE_TEXTURE);
+
         // Create enemies from map data
         let mut enemies = Vec::new();
         info!("Creating {} enemies from map data", enemy_spawns.len());
 
-        for (spawn_pos, patrol_points) in enemy_spawns {
-            if let Ok(idle_texture) =
-                engine::texture::Texture::load("assets/textures/weapons/gun2/idle.png")
-            {
-                if let Ok(fire_texture) =
-                    engine::texture::Texture::load("assets/textures/weapons/gun2/fire.png")
-                {
-                    info!("Creating ranged enemy at {:?}", spawn_pos);
-                    let mut enemy = Enemy::new_ranged(spawn_pos, idle_texture, fire_texture);
-                    info!("Setting patrol points: {:?}", patrol_points);
-                    enemy.set_patrol_points(patrol_points);
-                    enemies.push(enemy);
-                } else {
-                    error!("Failed to load enemy fire texture");
-                }
-            } else {
-                error!("Failed to load enemy idle texture");
-            }
+        // Sprites are loaded once per archetype and shared by its enemies
+        let mut archetype_sprites: HashMap<&str, Option<Rc<Sprite>>> = HashMap::new();
+        for spawn in enemy_spawns {
+            let Some((name, archetype)) = content.enemies.get_key_value(&spawn.enemy_type) else {
+                warn!("Skipping enemy with unknown type '{}'", spawn.enemy_type);
+                continue;
+            };
+            let sprite = archetype_sprites.entry(name.as_str()).or_insert_with(|| {
+                load_sprite(
+                    archetype.sprite.as_ref(),
+                    &archetype.idle_texture,
+                    archetype.fire_texture.as_deref(),
+                )
+            });
+            let Some(sprite) = sprite else {
+                continue;
+            };
+
+            info!("Creating {} enemy at {:?}", name, spawn.position);
+            let mut enemy = match archetype.kind.as_str() {
+                "melee" => Enemy::new_melee(spawn.position, Rc::clone(sprite)),
+                _ => Enemy::new_ranged(spawn.position, Rc::clone(sprite)),
+            };
+
+            // Map properties win over the archetype, which wins over the built-in defaults
+            let stats = spawn.properties.unwrap_or(EnemyProperties {
+                health: archetype.health.unwrap_or(enemy.health),
+                damage: archetype.damage.unwrap_or(enemy.get_damage()),
+                speed: archetype.speed.unwrap_or(enemy.speed),
+                attack_range: archetype.attack_range.unwrap_or(enemy.get_attack_range()),
+                chase_range: archetype.chase_range.unwrap_or(enemy.chase_range),
+            });
+            enemy.set_stats(
+                stats.health,
+                stats.damage,
+                stats.speed,
+                stats.attack_range,
+                stats.chase_range,
+            );
+            enemy.gib_threshold = archetype.gib_threshold;
+            enemy.archetype = name.clone();
+            enemy.squad = spawn.squad;
+
+            info!("Setting patrol points: {:?}", spawn.patrol_points);
+            enemy.set_patrol_points(spawn.patrol_points);
+            enemies.push(enemy);
         }
 
+        // Start at standing height over the spawn floor
+        let mut camera = Camera::new(world.spawn_point.x, world.spawn_point.y);
+        camera.z = world.heights.floor_at(world.spawn_point) + EYE_HEIGHT;
+
         Ok(Self {
-            camera: Camera::new(world.spawn_point.x, world.spawn_point.y),
+            camera,
             raycaster,
             last_update: Instant::now(),
             last_footstep: Instant::now(),
@@ -124,10 +392,15 @@ impl GameState {
             move_right: false,
             game_focused: true,
             world,
-            sound_manager,
-            music_player,
+            audio,
             game,
             enemies,
+            projectile_texture,
+            weapon_sound: format!("weapon.{}", weapon_name),
+            mods,
+            mod_menu: ModMenu::new(),
+            noises: Vec::new(),
+            squads: Squads::new(),
         })
     }
 
@@ -136,67 +409,163 @@ impl GameState {
         let dt = now.duration_since(self.last_update).as_secs_f32();
         self.last_update = now;
 
+        // Bring in the combat music layer while any enemy is fighting the player
+        let in_combat = self
+            .enemies
+            .iter()
+            .filter(|enemy| enemy.is_alive())
+            .any(|enemy| matches!(enemy.state, AIState::Chase | AIState::Attack));
+        self.audio.music.set_combat(in_combat);
+        self.audio.music.update(dt);
+
         if self.game_focused {
             let mut is_moving = false;
 
             // Update camera position based on movement flags
             if self.move_forward {
-                self.camera.move_forward(MOVE_SPEED * dt, &self.world.map);
+                self.camera.move_forward(
+                    MOVE_SPEED * dt,
+                    &self.world.map,
+                    &self.world.heights,
+                    &self.world.thin_walls,
+                );
                 is_moving = true;
             }
             if self.move_backward {
-                self.camera.move_forward(-MOVE_SPEED * dt, &self.world.map);
+                self.camera.move_forward(
+                    -MOVE_SPEED * dt,
+                    &self.world.map,
+                    &self.world.heights,
+                    &self.world.thin_walls,
+                );
                 is_moving = true;
             }
             if self.move_left {
-                self.camera.move_right(-MOVE_SPEED * dt, &self.world.map);
+                self.camera.move_right(
+                    -MOVE_SPEED * dt,
+                    &self.world.map,
+                    &self.world.heights,
+                    &self.world.thin_walls,
+                );
                 is_moving = true;
             }
             if self.move_right {
-                self.camera.move_right(MOVE_SPEED * dt, &self.world.map);
+                self.camera.move_right(
+                    MOVE_SPEED * dt,
+                    &self.world.map,
+                    &self.world.heights,
+                    &self.world.thin_walls,
+                );
                 is_moving = true;
             }
 
+            // Stepping into a portal carries the player through, turning with it
+            if let Some(fold) = self.world.portals.travel(self.camera.position) {
+                self.camera.travel(fold);
+            }
+
+            self.camera.update_height(&self.world.heights, dt);
+            self.audio.sounds.set_listener(self.camera.position);
+
             // Play footstep sound if moving
             if is_moving
                 && now.duration_since(self.last_footstep).as_secs_f32() >= FOOTSTEP_INTERVAL
             {
-                if let Err(e) = self.sound_manager.play_sound("step") {
+                if let Err(e) = self.audio.sounds.play_sound("step") {
                     error!("Failed to play footstep sound: {}", e);
                 }
                 self.last_footstep = now;
+                if !self.camera.crouching {
+                    let step = Noise::new(self.camera.position, FOOTSTEP_NOISE);
+                    self.noises.push(step);
+                }
             }
 
-            // Update weapon
-            if let Some(weapon) = &mut self.game.weapon {
-                weapon.update(dt, is_moving);
+            // Update weapon; the shot leaves on its fire clip's shoot frame
+            let shot = self.game.weapon.as_mut().and_then(|weapon| {
+                let fired = weapon.update(dt, is_moving);
+                fired.then(|| (weapon.projectile_speed(), weapon.damage()))
+            });
+            if let Some((speed, damage)) = shot {
+                self.fire_player_shot(speed, damage);
             }
 
-            // Update enemies and handle their projectiles
+            // Enemies hear the player's noises through open cells
+            for noise in self.noises.drain(..) {
+                let heard = noise.spread(&self.world.map, &self.world.portals);
+                for enemy in &mut self.enemies {
+                    let cell = enemy.position.floor();
+                    if let Some(&strength) = heard.get(&(cell.x as i32, cell.y as i32)) {
+                        enemy.hear(noise.position, strength);
+                    }
+                }
+            }
+
+            // Enemies pass on what they know of the player and pick their sides
+            self.squads.coordinate(
+                &mut self.enemies,
+                self.camera.position,
+                &self.world.map,
+                &self.world.portals,
+            );
+
+            // Update enemies and handle their projectiles and blows; they spot
+            // the player sooner when the player stands in the light
+            let lights = self.game.frame_lights();
+            let player_light = self.raycaster.light_level(&lights, self.camera.position);
             let mut i = 0;
             while i < self.enemies.len() {
-                if let Some((pos, vel, damage)) =
-                    self.enemies[i].update(self.camera.position, dt, &self.world.map)
-                {
-                    // Enemy wants to shoot
-                    if let Ok(projectile_texture) =
-                        engine::texture::Texture::load("assets/textures/particles/purple.png")
-                    {
-                        self.game.particles.add_particle(Particle::new(
-                            pos,
-                            vel,
-                            projectile_texture,
-                            damage,
-                            true,
-                        ));
-                        if let Err(e) = self.sound_manager.play_sound("gun2") {
-                            error!("Failed to play enemy gun sound: {}", e);
+                let action = self.enemies[i].update(
+                    self.camera.position,
+                    dt,
+                    &self.world.map,
+                    &self.world.portals,
+                    player_light,
+                );
+                let sound = format!("enemy.{}", self.enemies[i].archetype);
+                match action {
+                    Some(EnemyAction::Shoot {
+                        position: pos,
+                        velocity: vel,
+                        damage,
+                    }) => {
+                        // Enemy wants to shoot
+                        if let Some(projectile_texture) = &self.projectile_texture {
+                            let z = self.world.heights.floor_at(pos) + EYE_HEIGHT;
+                            self.game.particles.add_particle(
+                                Particle::new(pos, vel, projectile_texture.clone(), damage, true)
+                                    .with_z(z),
+                            );
+                            let (radius, intensity, duration) = MUZZLE_FLASH;
+                            self.game
+                                .add_light(PointLight::new(pos, radius, intensity), duration);
+                            if self.audio.sounds.has_sound(&sound) {
+                                if let Err(e) = self.audio.sounds.play_sound_at(&sound, pos) {
+                                    error!("Failed to play enemy gun sound: {}", e);
+                                }
+                            }
                         }
                     }
+                    Some(EnemyAction::Melee { damage, knockback }) => {
+                        self.game.take_damage(damage);
+                        self.camera.push(
+                            knockback,
+                            &self.world.map,
+                            &self.world.heights,
+                            &self.world.thin_walls,
+                        );
+                        if self.audio.sounds.has_sound(&sound) {
+                            let pos = self.enemies[i].position;
+                            if let Err(e) = self.audio.sounds.play_sound_at(&sound, pos) {
+                                error!("Failed to play enemy attack sound: {}", e);
+                            }
+                        }
+                    }
+                    None => {}
                 }
 
-                // Remove dead enemies
-                if !self.enemies[i].is_alive() {
+                // Remove dead enemies that leave no corpse
+                if self.enemies[i].is_gone() {
                     self.enemies.swap_remove(i);
                 } else {
                     i += 1;
@@ -204,7 +573,12 @@ impl GameState {
             }
 
             // Update particles and check collisions
-            self.game.particles.update(dt, &self.world.map);
+            self.game.particles.update(
+                dt,
+                &self.world.map,
+                &self.world.thin_walls,
+                &self.world.portals,
+            );
 
             // Collect particle effects
             let mut player_damage = 0;
@@ -214,13 +588,18 @@ impl GameState {
             for particle in self.game.particles.get_particles() {
                 if particle.from_enemy {
                     // Check if particle hits player
+                    // Shots can pass over a crouching player or under a jumping one
                     let to_player = self.camera.position - particle.position;
-                    if to_player.length() < 0.5 {
+                    let body = self.camera.feet()..=self.camera.z + HEAD_ROOM;
+                    if to_player.length() < 0.5 && body.contains(&particle.z) {
                         player_damage += particle.damage;
                     }
                 } else {
-                    // Check if particle hits enemies
+                    // Check if particle hits enemies; shots fly over corpses
                     for (i, enemy) in self.enemies.iter().enumerate() {
+                        if !enemy.is_alive() {
+                            continue;
+                        }
                         let to_enemy = enemy.position - particle.position;
                         if to_enemy.length() < 0.5 {
                             enemy_damages.push((i, particle.damage));
@@ -235,13 +614,32 @@ impl GameState {
             }
 
             for (enemy_idx, damage) in enemy_damages {
-                if let Some(enemy) = self.enemies.get_mut(enemy_idx) {
-                    enemy.take_damage(damage);
+                let Some(enemy) = self.enemies.get_mut(enemy_idx) else {
+                    continue;
+                };
+                if let Some(hurt) = enemy.take_damage(damage) {
+                    let sound = format!("enemy.{}.{}", enemy.archetype, hurt.hook());
+                    let position = enemy.position;
+                    if hurt != Hurt::Pain {
+                        // The death cry cuts off the pain one
+                        let pain = format!("enemy.{}.{}", enemy.archetype, Hurt::Pain.hook());
+                        self.audio.sounds.stop_sound(&pain);
+                    }
+                    if self.audio.sounds.has_sound(&sound) {
+                        if let Err(e) = self.audio.sounds.play_sound_at(&sound, position) {
+                            error!("Failed to play enemy {} sound: {}", hurt.hook(), e);
+                        }
+                    }
                 }
             }
 
             // Update game state
-            self.game.update(dt, &self.world.map);
+            self.game.update(
+                dt,
+                &self.world.map,
+                &self.world.thin_walls,
+                &self.world.portals,
+            );
         }
     }
 
@@ -254,12 +652,14 @@ impl GameState {
             pixel[3] = 0xFF; // A
         }
 
-        // Get particles for rendering
+        // Get particles and lights for rendering
         let particles = self.game.particles.get_particles();
+        let lights = self.game.frame_lights();
 
         // Render world, enemies, and particles
+        self.raycaster.set_time(self.game.time);
         self.raycaster
-            .render(&self.camera, &self.enemies, particles, frame);
+            .render(&self.camera, &self.enemies, particles, &lights, frame);
 
         // Render weapon on top
         if let Some(weapon) = &self.game.weapon {
@@ -268,6 +668,10 @@ impl GameState {
 
         // Render health bar
         self.game.render(frame);
+
+        if self.mod_menu.visible {
+            self.mod_menu.render(frame, WIDTH, &self.mods);
+        }
     }
 
     fn handle_key_event(&mut self, key_code: VirtualKeyCode, pressed: bool) -> Option<bool> {
@@ -296,6 +700,56 @@ impl GameState {
                 }
                 None
             }
+            VirtualKeyCode::Space if pressed && self.game_focused => {
+                self.camera.jump();
+                None
+            }
+            VirtualKeyCode::LControl | VirtualKeyCode::C => {
+                if self.game_focused {
+                    self.camera.set_crouching(pressed);
+                }
+                None
+            }
+            VirtualKeyCode::F1 if pressed => {
+                self.mod_menu.toggle();
+                None
+            }
+            VirtualKeyCode::Up if pressed && self.mod_menu.visible => {
+                self.mod_menu.select_previous();
+                None
+            }
+            VirtualKeyCode::Down if pressed && self.mod_menu.visible => {
+                self.mod_menu.select_next(&self.mods);
+                None
+            }
+            VirtualKeyCode::Return if pressed && self.mod_menu.visible => {
+                self.mod_menu.toggle_selected(&mut self.mods);
+                None
+            }
+            VirtualKeyCode::F5 if pressed => {
+                self.toggle_bus_mute(Bus::Music);
+                None
+            }
+            VirtualKeyCode::F6 if pressed => {
+                self.toggle_bus_mute(Bus::Effects);
+                None
+            }
+            VirtualKeyCode::F7 if pressed => {
+                self.toggle_bus_mute(Bus::Weapons);
+                None
+            }
+            VirtualKeyCode::F8 if pressed => {
+                self.toggle_bus_solo(Bus::Music);
+                None
+            }
+            VirtualKeyCode::F9 if pressed => {
+                self.toggle_bus_solo(Bus::Effects);
+                None
+            }
+            VirtualKeyCode::F10 if pressed => {
+                self.toggle_bus_solo(Bus::Weapons);
+                None
+            }
             VirtualKeyCode::Escape if pressed => {
                 // Toggle game focus
                 self.game_focused = !self.game_focused;
@@ -305,6 +759,7 @@ impl GameState {
                     self.move_backward = false;
                     self.move_left = false;
                     self.move_right = false;
+                    self.camera.set_crouching(false);
                 }
                 Some(self.game_focused)
             }
@@ -312,40 +767,70 @@ impl GameState {
         }
     }
 
-    fn handle_mouse_motion(&mut self, delta_x: f64) {
+    // Debug helpers for checking the mix one bus at a time
+    fn toggle_bus_mute(&mut self, bus: Bus) {
+        let muted = self.audio.mixer().toggle_mute(bus);
+        info!("{:?} bus {}", bus, if muted { "muted" } else { "unmuted" });
+    }
+
+    fn toggle_bus_solo(&mut self, bus: Bus) {
+        let solo = self.audio.mixer().toggle_solo(bus);
+        info!("{:?} bus solo {}", bus, if solo { "on" } else { "off" });
+    }
+
+    fn handle_mouse_motion(&mut self, delta_x: f64, delta_y: f64) {
         if self.game_focused {
             self.camera.rotate(-delta_x as f32 * MOUSE_SENSITIVITY);
+            self.camera.look_up(-delta_y as f32 * MOUSE_SENSITIVITY);
         }
     }
 
     fn handle_mouse_input(&mut self, button: MouseButton, pressed: bool) {
         if self.game_focused && button == MouseButton::Left && pressed {
             if let Some(weapon) = &mut self.game.weapon {
-                if weapon.fire() {
-                    if let Err(e) = self.sound_manager.play_sound("gun1") {
-                        error!("Failed to play gun sound: {}", e);
-                    }
-
-                    // Create player projectile
-                    if let Ok(projectile_texture) =
-                        engine::texture::Texture::load("assets/textures/particles/purple.png")
-                    {
-                        self.game.particles.add_particle(Particle::new(
-                            self.camera.position,
-                            self.camera.direction * 10.0,
-                            projectile_texture,
-                            20,
-                            false,
-                        ));
-                    }
-                }
+                weapon.fire();
             }
         }
     }
+
+    fn fire_player_shot(&mut self, projectile_speed: f32, damage: i32) {
+        let gunshot = Noise::new(self.camera.position, GUNSHOT_NOISE);
+        self.noises.push(gunshot);
+
+        if let Err(e) = self.audio.sounds.play_sound(&self.weapon_sound) {
+            error!("Failed to play gun sound: {}", e);
+        }
+
+        let (radius, intensity, duration) = MUZZLE_FLASH;
+        let flash_pos = self.camera.position + self.camera.direction * 0.5;
+        let flash = PointLight::new(flash_pos, radius, intensity);
+        self.game.lights.push(DynamicLight::new(flash, duration));
+
+        // Create player projectile
+        if let Some(projectile_texture) = &self.projectile_texture {
+            self.game.particles.add_particle(
+                Particle::new(
+                    self.camera.position,
+                    self.camera.direction * projectile_speed,
+                    projectile_texture.clone(),
+                    damage,
+                    false,
+                )
+                .with_z(self.camera.z - PROJECTILE_DROP),
+            );
+        }
+    }
 }
 
 fn main() -> Result<()> {
     env_logger::init();
+
+    // Tool subcommands (e.g. `pack`) run and exit without opening a window
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(result) = cli::run(&args) {
+        return result;
+    }
+
     let event_loop = EventLoop::new();
 
     let window = {
@@ -358,8 +843,19 @@ fn main() -> Result<()> {
             .unwrap()
     };
 
+    // Mods mount on top of the base data and packs in load order
+    let mut vfs = Vfs::with_base_data();
+    let mods_dir = vfs.base_dir().unwrap_or(Path::new(".")).join(MODS_DIR);
+    let mut mods = ModList::discover(mods_dir);
+    mods.mount(&mut vfs);
+
+    let config = Config::load(&vfs, "config/default.toml").unwrap_or_else(|e| {
+        error!("Failed to load config, using defaults: {}", e);
+        Config::default()
+    });
+
     // Initialize audio
-    let (_stream, stream_handle) = rodio::OutputStream::try_default()
+    let audio = AudioManager::new(&config.audio)
         .map_err(|e| anyhow::anyhow!("Failed to initialize audio: {}", e))?;
 
     // Start with cursor hidden and captured
@@ -369,7 +865,7 @@ fn main() -> Result<()> {
     let window_size = window.inner_size();
     let surface_texture = SurfaceTexture::new(window_size.width, window_size.height, &window);
     let mut pixels = Pixels::new(WIDTH, HEIGHT, surface_texture)?;
-    let mut game = GameState::new(stream_handle)?;
+    let mut game = GameState::new(&vfs, &config, audio, mods)?;
 
     event_loop.run(move |event, _, control_flow| {
         *control_flow = ControlFlow::Poll;
@@ -404,10 +900,10 @@ fn main() -> Result<()> {
                 game.handle_mouse_input(button, state == ElementState::Pressed);
             }
             Event::DeviceEvent {
-                event: DeviceEvent::MouseMotion { delta: (x, _) },
+                event: DeviceEvent::MouseMotion { delta: (x, y) },
                 ..
             } => {
-                game.handle_mouse_motion(x);
+                game.handle_mouse_motion(x, y);
             }
             Event::WindowEvent {
                 event: WindowEvent::Resized(size),
@@ -432,6 +928,9 @@ fn main() -> Result<()> {
 
                 window.request_redraw();
             }
+            Event::LoopDestroyed => {
+                game.audio.sounds.cleanup();
+            }
             _ => (),
         }
     });
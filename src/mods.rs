@@ -0,0 +1,336 @@
+use crate::assets::{pak, Vfs};
+use crate::game::content::{Content, ContentDef};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const MODS_DIR: &str = "mods";
+const MANIFEST_FILE: &str = "mod.toml";
+const SETTINGS_FILE: &str = "mods.toml";
+// Mods mount above the base data (0) and packs (1..)
+const MOD_PRIORITY: i32 = 100;
+
+#[derive(Debug, Deserialize)]
+struct ModInfoDef {
+    name: String,
+    version: String,
+    #[serde(default)]
+    dependencies: Vec<String>,
+    #[serde(default)]
+    load_order: i32,
+}
+
+/// `mods/<id>/mod.toml`: a `[mod]` table plus optional content definitions
+#[derive(Debug, Deserialize)]
+struct ManifestFile {
+    #[serde(rename = "mod")]
+    info: ModInfoDef,
+    #[serde(flatten)]
+    content: ContentDef,
+}
+
+/// Which mods the player switched off, kept in `mods/mods.toml`
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ModSettings {
+    #[serde(default)]
+    disabled: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ModStatus {
+    Active,
+    Disabled,
+    MissingDependency(String),
+    Invalid(String),
+}
+
+pub struct ModEntry {
+    pub id: String, // Directory name
+    pub name: String,
+    pub version: String,
+    pub load_order: i32,
+    pub dependencies: Vec<String>,
+    pub enabled: bool,
+    pub status: ModStatus,
+    dir: PathBuf,
+    content: ContentDef,
+}
+
+impl ModEntry {
+    fn load(dir: &Path) -> Result<Self, String> {
+        let id = dir
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let manifest_path = dir.join(MANIFEST_FILE);
+        let text = fs::read_to_string(&manifest_path)
+            .map_err(|e| format!("Failed to read {}: {}", manifest_path.display(), e))?;
+        let manifest: ManifestFile = toml::from_str(&text)
+            .map_err(|e| format!("Failed to parse {}: {}", manifest_path.display(), e))?;
+
+        Ok(Self {
+            id,
+            name: manifest.info.name,
+            version: manifest.info.version,
+            load_order: manifest.info.load_order,
+            dependencies: manifest.info.dependencies,
+            enabled: true,
+            status: ModStatus::Active,
+            dir: dir.to_path_buf(),
+            content: manifest.content,
+        })
+    }
+
+    fn invalid(dir: &Path, error: String) -> Self {
+        let id = dir
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        Self {
+            name: id.clone(),
+            id,
+            version: String::new(),
+            load_order: 0,
+            dependencies: Vec::new(),
+            enabled: true,
+            status: ModStatus::Invalid(error),
+            dir: dir.to_path_buf(),
+            content: ContentDef::default(),
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.status == ModStatus::Active
+    }
+}
+
+/// Mods found in the mods directory, sorted by load order.
+/// Later mods override files and definitions from earlier ones.
+pub struct ModList {
+    dir: PathBuf,
+    pub mods: Vec<ModEntry>,
+    pub conflicts: Vec<String>,
+}
+
+impl ModList {
+    /// Scan `dir` for mod folders and work out which of them can be loaded
+    pub fn discover<P: AsRef<Path>>(dir: P) -> Self {
+        let dir = dir.as_ref().to_path_buf();
+        let settings: ModSettings = fs::read_to_string(dir.join(SETTINGS_FILE))
+            .ok()
+            .and_then(|text| toml::from_str(&text).ok())
+            .unwrap_or_default();
+
+        let mut mods: Vec<ModEntry> = fs::read_dir(&dir)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|entry| entry.path())
+                    .filter(|path| path.join(MANIFEST_FILE).is_file())
+                    .map(|path| {
+                        ModEntry::load(&path).unwrap_or_else(|e| ModEntry::invalid(&path, e))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        mods.sort_by(|a, b| a.load_order.cmp(&b.load_order).then(a.id.cmp(&b.id)));
+
+        for entry in &mut mods {
+            entry.enabled = !settings.disabled.contains(&entry.id);
+            if !entry.enabled && entry.is_active() {
+                entry.status = ModStatus::Disabled;
+            }
+        }
+
+        let mut list = Self {
+            dir,
+            mods,
+            conflicts: Vec::new(),
+        };
+        list.resolve_dependencies();
+        list
+    }
+
+    /// Deactivate mods whose dependencies are missing or inactive, repeating
+    /// until nothing changes so that chains of dependencies are handled.
+    fn resolve_dependencies(&mut self) {
+        loop {
+            let active: HashSet<String> = self
+                .mods
+                .iter()
+                .filter(|entry| entry.is_active())
+                .map(|entry| entry.id.clone())
+                .collect();
+
+            let mut changed = false;
+            for entry in self.mods.iter_mut().filter(|entry| entry.is_active()) {
+                if let Some(missing) = entry
+                    .dependencies
+                    .iter()
+                    .find(|dependency| !active.contains(*dependency))
+                {
+                    entry.status = ModStatus::MissingDependency(missing.clone());
+                    changed = true;
+                }
+            }
+
+            if !changed {
+                break;
+            }
+        }
+
+        for (i, entry) in self.mods.iter().enumerate() {
+            for dependency in &entry.dependencies {
+                let later = self.mods[i + 1..]
+                    .iter()
+                    .any(|other| &other.id == dependency && other.is_active());
+                if entry.is_active() && later {
+                    self.conflicts.push(format!(
+                        "{} loads before its dependency {}; raise its load_order",
+                        entry.id, dependency
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Mount every active mod into the VFS and note files provided by more than one mod
+    pub fn mount(&mut self, vfs: &mut Vfs) {
+        let mut owners: HashMap<String, String> = HashMap::new();
+
+        for (i, entry) in self
+            .mods
+            .iter()
+            .filter(|entry| entry.is_active())
+            .enumerate()
+        {
+            vfs.mount_dir(&entry.dir, MOD_PRIORITY + i as i32);
+
+            let mut files = Vec::new();
+            if let Err(e) = pak::collect_files(&entry.dir, &entry.dir, &mut files) {
+                warn!("Failed to list files of mod {}: {}", entry.id, e);
+            }
+            for (path, _) in files {
+                if path.eq_ignore_ascii_case(MANIFEST_FILE) {
+                    continue;
+                }
+                if let Some(previous) = owners.insert(path.to_lowercase(), entry.id.clone()) {
+                    self.conflicts.push(format!(
+                        "File '{}' from {} is overridden by {}",
+                        path, previous, entry.id
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Layer each active mod's enemy archetypes and weapons over the base content
+    pub fn apply_content(&mut self, content: &mut Content) {
+        for entry in self.mods.iter().filter(|entry| entry.is_active()) {
+            let conflicts = content.merge(entry.content.clone(), &entry.id);
+            self.conflicts.extend(conflicts);
+        }
+    }
+
+    /// Log the load order and every conflict found at startup
+    pub fn log(&self) {
+        if self.mods.is_empty() {
+            return;
+        }
+
+        for entry in &self.mods {
+            let status = match &entry.status {
+                ModStatus::Active => "active".to_string(),
+                ModStatus::Disabled => "disabled".to_string(),
+                ModStatus::MissingDependency(dependency) => {
+                    format!("not loaded, missing dependency '{}'", dependency)
+                }
+                ModStatus::Invalid(error) => format!("not loaded, {}", error),
+            };
+            info!(
+                "Mod {} ({} {}, load order {}): {}",
+                entry.id, entry.name, entry.version, entry.load_order, status
+            );
+        }
+
+        if !self.conflicts.is_empty() {
+            warn!(
+                "{} mod conflicts:\n  {}",
+                self.conflicts.len(),
+                self.conflicts.join("\n  ")
+            );
+        }
+    }
+
+    /// Enable or disable a mod for the next start and save the choice
+    pub fn set_enabled(&mut self, index: usize, enabled: bool) -> Result<(), String> {
+        if let Some(entry) = self.mods.get_mut(index) {
+            entry.enabled = enabled;
+        }
+
+        let settings = ModSettings {
+            disabled: self
+                .mods
+                .iter()
+                .filter(|entry| !entry.enabled)
+                .map(|entry| entry.id.clone())
+                .collect(),
+        };
+        let text = toml::to_string(&settings)
+            .map_err(|e| format!("Failed to serialize mod settings: {}", e))?;
+        let path = self.dir.join(SETTINGS_FILE);
+        fs::write(&path, text).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn write_mod(root: &Path, id: &str, manifest: &str) {
+        let dir = root.join(id);
+        fs::create_dir_all(dir.join("assets/maps")).unwrap();
+        fs::write(dir.join(MANIFEST_FILE), manifest).unwrap();
+        fs::write(dir.join("assets/maps/test.toml"), "").unwrap();
+    }
+
+    #[test]
+    fn test_load_order_dependencies_and_conflicts() {
+        let root = env::temp_dir().join("lucid_mods_test");
+        let _ = fs::remove_dir_all(&root);
+        write_mod(
+            &root,
+            "late",
+            "[mod]\nname = \"Late\"\nversion = \"1.0\"\nload_order = 5\ndependencies = [\"early\"]",
+        );
+        write_mod(&root, "early", "[mod]\nname = \"Early\"\nversion = \"1.0\"");
+        write_mod(
+            &root,
+            "orphan",
+            "[mod]\nname = \"Orphan\"\nversion = \"1.0\"\ndependencies = [\"missing\"]",
+        );
+
+        let mut mods = ModList::discover(&root);
+        let ids: Vec<&str> = mods.mods.iter().map(|entry| entry.id.as_str()).collect();
+        assert_eq!(ids, ["early", "orphan", "late"]);
+        assert_eq!(
+            mods.mods[1].status,
+            ModStatus::MissingDependency("missing".to_string())
+        );
+
+        let mut vfs = Vfs::new();
+        mods.mount(&mut vfs);
+        assert_eq!(mods.conflicts.len(), 1);
+
+        // Disabling a dependency takes its dependents down with it on the next start
+        mods.set_enabled(0, false).unwrap();
+        let mods = ModList::discover(&root);
+        assert_eq!(mods.mods[0].status, ModStatus::Disabled);
+        assert!(!mods.mods[2].is_active());
+
+        let _ = fs::remove_dir_all(root);
+    }
+}